@@ -0,0 +1,11 @@
+-- Create operation_queue table for pending and running Flatpak operations
+CREATE TABLE IF NOT EXISTS operation_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    scope TEXT NOT NULL,
+    task TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_operation_queue_scope_position ON operation_queue(scope, status, position);
@@ -0,0 +1,21 @@
+-- Create history table recording every Flatpak operation the hub has run
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    ref_name TEXT NOT NULL,
+    app_id TEXT,
+    scope TEXT NOT NULL,
+    from_commit TEXT,
+    to_commit TEXT,
+    bytes_downloaded INTEGER NOT NULL DEFAULT 0,
+    started_at INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    outcome TEXT NOT NULL,
+    error_code TEXT,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_history_started_at ON history(started_at);
+CREATE INDEX IF NOT EXISTS idx_history_ref_name ON history(ref_name, started_at);
+CREATE INDEX IF NOT EXISTS idx_history_app_id ON history(app_id, started_at);
@@ -0,0 +1,5 @@
+-- Create settings table for installer preferences the backend needs at startup
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
@@ -89,6 +89,41 @@ pub async fn get_db_pool() -> Result<SqlitePool, String> {
         }
     }
     
+    sqlx::query("CREATE TABLE IF NOT EXISTS operation_queue (id INTEGER PRIMARY KEY AUTOINCREMENT, scope TEXT NOT NULL, task TEXT NOT NULL, position INTEGER NOT NULL, status TEXT NOT NULL DEFAULT 'pending', created_at INTEGER NOT NULL)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create operation_queue table: {}", e))?;
+    
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_operation_queue_scope_position ON operation_queue(scope, status, position)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create index: {}", e))?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS history (id INTEGER PRIMARY KEY AUTOINCREMENT, operation_id TEXT NOT NULL, action TEXT NOT NULL, ref_name TEXT NOT NULL, app_id TEXT, scope TEXT NOT NULL, from_commit TEXT, to_commit TEXT, bytes_downloaded INTEGER NOT NULL DEFAULT 0, started_at INTEGER NOT NULL, duration_ms INTEGER NOT NULL DEFAULT 0, outcome TEXT NOT NULL, error_code TEXT, error TEXT)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create history table: {}", e))?;
+    
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_history_started_at ON history(started_at)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create index: {}", e))?;
+    
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_history_ref_name ON history(ref_name, started_at)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create index: {}", e))?;
+    
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_history_app_id ON history(app_id, started_at)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create index: {}", e))?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create settings table: {}", e))?;
+    
     *pool_guard = Some(pool.clone());
     Ok(pool)
 }
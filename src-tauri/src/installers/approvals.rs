@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use libflatpak::{Installation, InstalledRef, Transaction, prelude::*};
+use serde::Serialize;
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_installed, locate_installed};
+use super::operations::OperationGuard;
+use super::permissions::{CONTEXT_KEYS, SandboxPermissions, fetch_remote_metadata, load_installed_metadata, parse_permissions};
+use super::settings;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionChange {
+    pub ref_name: String,
+    /// Set for a new install, whose permissions are all listed as added.
+    pub install: bool,
+    pub added: SandboxPermissions,
+    pub removed: SandboxPermissions,
+    pub widens: bool,
+    pub risky: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionRequest {
+    pub request_id: String,
+    pub operation_id: String,
+    pub change: PermissionChange,
+}
+
+struct PendingRequest {
+    request: PermissionRequest,
+    responder: Sender<bool>,
+}
+
+static AUTO_APPROVE_NON_WIDENING: AtomicBool = AtomicBool::new(false);
+static PENDING: OnceLock<Mutex<HashMap<String, PendingRequest>>> = OnceLock::new();
+
+fn pending() -> &'static Mutex<HashMap<String, PendingRequest>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the permissions `new` has that `old` does not. Bus names count
+/// as added when their policy changes to anything but `none`.
+fn added_permissions(old: &SandboxPermissions, new: &SandboxPermissions) -> SandboxPermissions {
+    let mut added = SandboxPermissions::default();
+
+    for key in CONTEXT_KEYS {
+        let (Some(old_list), Some(new_list)) = (old.context_list(key), new.context_list(key)) else {
+            continue;
+        };
+        if let Some(list) = added.context_list_mut(key) {
+            *list = new_list.iter().filter(|entry| !old_list.contains(entry)).cloned().collect();
+        }
+    }
+
+    added.session_bus = new.session_bus.iter()
+        .filter(|(name, policy)| *policy != "none" && old.session_bus.get(*name) != Some(*policy))
+        .map(|(name, policy)| (name.clone(), policy.clone()))
+        .collect();
+    added.system_bus = new.system_bus.iter()
+        .filter(|(name, policy)| *policy != "none" && old.system_bus.get(*name) != Some(*policy))
+        .map(|(name, policy)| (name.clone(), policy.clone()))
+        .collect();
+    added.environment = new.environment.iter()
+        .filter(|(name, value)| old.environment.get(*name) != Some(*value))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    added
+}
+
+pub fn diff_permissions(ref_name: &str, old: &SandboxPermissions, new: &SandboxPermissions) -> Option<PermissionChange> {
+    let added = added_permissions(old, new);
+    let removed = added_permissions(new, old);
+    let empty = SandboxPermissions::default();
+
+    if added == empty && removed == empty {
+        return None;
+    }
+
+    Some(PermissionChange {
+        ref_name: ref_name.to_string(),
+        install: false,
+        widens: added != empty,
+        risky: added.risky(),
+        added,
+        removed,
+    })
+}
+
+/// Compares an installed ref's metadata with the commit its origin would
+/// update it to.
+pub(super) fn update_permission_change(installation: &Installation, installed_ref: &InstalledRef, cancellable: &libflatpak::gio::Cancellable) -> Result<Option<PermissionChange>, InstallerError> {
+    if installed_ref.kind() != libflatpak::RefKind::App {
+        return Ok(None);
+    }
+
+    let full_ref = installed_ref.format_ref()
+        .map(|s| s.to_string())
+        .ok_or_else(|| InstallerError::Failed("Failed to format installed ref".to_string()))?;
+    let origin = installed_ref.origin()
+        .map(|s| s.to_string())
+        .ok_or_else(|| InstallerError::Failed(format!("{} has no origin remote", full_ref)))?;
+
+    let old = parse_permissions(&load_installed_metadata(installed_ref, cancellable)?);
+    let new = parse_permissions(&fetch_remote_metadata(installation, &origin, &full_ref, cancellable)?);
+
+    Ok(diff_permissions(&full_ref, &old, &new))
+}
+
+/// Blocks the operation until the frontend answers a
+/// `flatpak-permissions-requested` event. Cancelling the operation declines.
+fn request_approval(app: &AppHandle, operation_id: &str, cancellable: &libflatpak::gio::Cancellable, change: PermissionChange) -> Result<bool, InstallerError> {
+    let request = PermissionRequest {
+        request_id: format!("{}:{}", operation_id, change.ref_name),
+        operation_id: operation_id.to_string(),
+        change,
+    };
+    let (responder, receiver) = mpsc::channel();
+
+    pending().lock().unwrap().insert(request.request_id.clone(), PendingRequest { request: request.clone(), responder });
+
+    if let Err(e) = app.emit("flatpak-permissions-requested", &request) {
+        pending().lock().unwrap().remove(&request.request_id);
+        return Err(InstallerError::Failed(e.to_string()));
+    }
+
+    let approved = loop {
+        match receiver.recv_timeout(Duration::from_millis(250)) {
+            Ok(approved) => break approved,
+            Err(RecvTimeoutError::Timeout) if !cancellable.is_cancelled() => continue,
+            Err(_) => break false,
+        }
+    };
+
+    pending().lock().unwrap().remove(&request.request_id);
+    Ok(approved)
+}
+
+/// Decides whether an update may proceed. Updates that don't change the
+/// sandbox always may; non-widening ones may when auto-approval is enabled;
+/// anything else needs the user's approval.
+pub(super) fn approve_update(app: &AppHandle, operation: &OperationGuard, installation: &Installation, installed_ref: &InstalledRef) -> Result<bool, InstallerError> {
+    match update_permission_change(installation, installed_ref, operation.cancellable())? {
+        Some(change) => approve_change(app, operation.id(), operation.cancellable(), change),
+        None => Ok(true),
+    }
+}
+
+fn approve_change(app: &AppHandle, operation_id: &str, cancellable: &libflatpak::gio::Cancellable, change: PermissionChange) -> Result<bool, InstallerError> {
+    if !change.widens && AUTO_APPROVE_NON_WIDENING.load(Ordering::Relaxed) {
+        return Ok(true);
+    }
+
+    let ref_name = change.ref_name.clone();
+    let approved = request_approval(app, operation_id, cancellable, change)?;
+
+    if !approved {
+        app.emit("flatpak-update-declined", serde_json::json!({ "ref": ref_name, "operation_id": operation_id }))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(approved)
+}
+
+/// Reviews an update to an explicit commit. The remote summary only carries
+/// the latest commit's metadata, so this waits for the transaction to resolve
+/// the target commit and diffs the metadata of the resolved operation; a
+/// declined change aborts the transaction from its `ready` handler.
+pub(super) fn approve_on_ready(tx: &Transaction, app: &AppHandle, operation: &OperationGuard, installed_ref: &InstalledRef) -> Result<(), InstallerError> {
+    if installed_ref.kind() != libflatpak::RefKind::App {
+        return Ok(());
+    }
+
+    let full_ref = installed_ref.format_ref()
+        .map(|s| s.to_string())
+        .ok_or_else(|| InstallerError::Failed("Failed to format installed ref".to_string()))?;
+    let old = parse_permissions(&load_installed_metadata(installed_ref, operation.cancellable())?);
+
+    let app = app.clone();
+    let operation_id = operation.id().to_string();
+    let cancellable = operation.cancellable().clone();
+    tx.connect_ready(move |tx| {
+        let metadata = tx.operations()
+            .iter()
+            .find(|op| op.get_ref().is_some_and(|r| r == full_ref.as_str()))
+            .and_then(|op| op.metadata());
+        let Some(metadata) = metadata else {
+            eprintln!("No metadata resolved for {}; declining the update", full_ref);
+            return false;
+        };
+
+        let new = parse_permissions(&metadata.to_data());
+        let Some(change) = diff_permissions(&full_ref, &old, &new) else {
+            return true;
+        };
+
+        approve_change(&app, &operation_id, &cancellable, change).unwrap_or_else(|e| {
+            eprintln!("Failed to review permissions of {}: {}", full_ref, e);
+            false
+        })
+    });
+
+    Ok(())
+}
+
+/// Reviews an install whose ref can't be planned up front, such as a
+/// `.flatpakref` that brings its own remote. Once the transaction has
+/// resolved the app, risky permissions need the user's approval before
+/// anything is deployed.
+pub(super) fn approve_install_on_ready(tx: &Transaction, app: &AppHandle, operation: &OperationGuard, full_ref: &str) {
+    let app = app.clone();
+    let operation_id = operation.id().to_string();
+    let cancellable = operation.cancellable().clone();
+    let full_ref = full_ref.to_string();
+    tx.connect_ready(move |tx| {
+        let metadata = tx.operations()
+            .iter()
+            .find(|op| op.get_ref().is_some_and(|r| r == full_ref.as_str()))
+            .and_then(|op| op.metadata());
+        let Some(metadata) = metadata else {
+            eprintln!("No metadata resolved for {}; declining the install", full_ref);
+            return false;
+        };
+
+        let permissions = parse_permissions(&metadata.to_data());
+        let risky = permissions.risky();
+        if risky.is_empty() {
+            return true;
+        }
+
+        let change = PermissionChange {
+            ref_name: full_ref.clone(),
+            install: true,
+            added: permissions,
+            removed: SandboxPermissions::default(),
+            widens: true,
+            risky,
+        };
+        let approved = request_approval(&app, &operation_id, &cancellable, change).unwrap_or_else(|e| {
+            eprintln!("Failed to review permissions of {}: {}", full_ref, e);
+            false
+        });
+        if !approved {
+            let _ = app.emit("flatpak-install-declined", serde_json::json!({ "ref": full_ref, "operation_id": operation_id }));
+        }
+        approved
+    });
+}
+
+fn review_update_permissions(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<Option<PermissionChange>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+    let (_, installation) = locate_installed(scope.unwrap_or(InstallationScope::Both), spec, &cancellable)?;
+    let installed_ref = find_installed(&installation, spec, &cancellable)?;
+
+    update_permission_change(&installation, &installed_ref, &cancellable)
+}
+
+#[tauri::command]
+pub async fn check_update_permissions(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<Option<PermissionChange>, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || review_update_permissions(ref_name, scope, branch, arch))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to check update permissions: {}", e)))?
+}
+
+#[tauri::command]
+pub fn respond_permission_request(request_id: String, approved: bool) -> Result<bool, InstallerError> {
+    match pending().lock().unwrap().get(&request_id) {
+        Some(pending) => Ok(pending.responder.send(approved).is_ok()),
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub fn list_permission_requests() -> Result<Vec<PermissionRequest>, InstallerError> {
+    Ok(pending().lock().unwrap().values().map(|pending| pending.request.clone()).collect())
+}
+
+pub(super) fn set_auto_approve(enabled: bool) {
+    AUTO_APPROVE_NON_WIDENING.store(enabled, Ordering::Relaxed);
+}
+
+#[tauri::command]
+pub fn get_auto_approve_non_widening_updates() -> Result<bool, InstallerError> {
+    Ok(AUTO_APPROVE_NON_WIDENING.load(Ordering::Relaxed))
+}
+
+#[tauri::command]
+pub async fn set_auto_approve_non_widening_updates(enabled: bool) -> Result<(), InstallerError> {
+    settings::store_setting(settings::AUTO_APPROVE_NON_WIDENING_KEY, if enabled { "true" } else { "false" }).await?;
+    set_auto_approve(enabled);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "[Application]\n\
+        name=org.example.App\n\
+        \n\
+        [Context]\n\
+        shared=network;ipc;\n\
+        sockets=x11;wayland;\n\
+        filesystems=xdg-download;\n\
+        \n\
+        [Session Bus Policy]\n\
+        org.freedesktop.Notifications=talk\n\
+        \n\
+        [Environment]\n\
+        LANG=C\n";
+
+    #[test]
+    fn unchanged_metadata_has_no_diff() {
+        let old = parse_permissions(OLD);
+        assert!(diff_permissions("app/org.example.App/x86_64/stable", &old, &old.clone()).is_none());
+    }
+
+    #[test]
+    fn added_permissions_widen() {
+        let old = parse_permissions(OLD);
+        let new = parse_permissions(&OLD
+            .replace("filesystems=xdg-download;", "filesystems=xdg-download;host;")
+            .replace("org.freedesktop.Notifications=talk", "org.freedesktop.Notifications=talk\norg.freedesktop.Flatpak=talk"));
+
+        let change = diff_permissions("app/org.example.App/x86_64/stable", &old, &new).unwrap();
+        assert!(change.widens);
+        assert_eq!(change.added.filesystems, ["host"]);
+        assert_eq!(change.added.session_bus.keys().collect::<Vec<_>>(), ["org.freedesktop.Flatpak"]);
+        assert_eq!(change.removed, SandboxPermissions::default());
+        assert_eq!(change.risky, ["--filesystem=host", "--talk-name=org.freedesktop.Flatpak"]);
+    }
+
+    #[test]
+    fn removed_permissions_do_not_widen() {
+        let old = parse_permissions(OLD);
+        let new = parse_permissions(&OLD
+            .replace("sockets=x11;wayland;", "sockets=wayland;")
+            .replace("org.freedesktop.Notifications=talk", "org.freedesktop.Notifications=none"));
+
+        let change = diff_permissions("app/org.example.App/x86_64/stable", &old, &new).unwrap();
+        assert!(!change.widens);
+        assert!(change.risky.is_empty());
+        assert_eq!(change.added, SandboxPermissions::default());
+        assert_eq!(change.removed.sockets, ["x11"]);
+        assert_eq!(change.removed.session_bus.get("org.freedesktop.Notifications").map(String::as_str), Some("talk"));
+    }
+
+    #[test]
+    fn changed_values_count_both_ways() {
+        let old = parse_permissions(OLD);
+        let new = parse_permissions(&OLD
+            .replace("LANG=C", "LANG=C.UTF-8")
+            .replace("org.freedesktop.Notifications=talk", "org.freedesktop.Notifications=own"));
+
+        let change = diff_permissions("app/org.example.App/x86_64/stable", &old, &new).unwrap();
+        assert!(change.widens);
+        assert_eq!(change.added.environment.get("LANG").map(String::as_str), Some("C.UTF-8"));
+        assert_eq!(change.removed.environment.get("LANG").map(String::as_str), Some("C"));
+        assert_eq!(change.added.session_bus.get("org.freedesktop.Notifications").map(String::as_str), Some("own"));
+    }
+}
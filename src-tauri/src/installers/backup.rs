@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use libflatpak::prelude::*;
+use serde::{Deserialize, Serialize};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_installed, locate_installed};
+use super::userdata::{self, data_dir, dir_size};
+
+const MANIFEST_NAME: &str = "manifest.json";
+const DATA_PREFIX: &str = "data";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub app_id: String,
+    pub branch: String,
+    pub arch: String,
+    pub commit: String,
+    pub origin: String,
+    pub scope: InstallationScope,
+    pub created_at: i64,
+    pub size: u64,
+}
+
+fn describe_app(app_id: &str, scope: InstallationScope, branch: Option<&str>, arch: Option<&str>) -> Result<BackupManifest, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(app_id, arch, branch);
+
+    let (scope, installation) = locate_installed(scope, spec, &cancellable)?;
+    let installed_ref = find_installed(&installation, spec, &cancellable)?;
+
+    Ok(BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_id: spec.id.to_string(),
+        branch: installed_ref.branch().map(|s| s.to_string()).unwrap_or_default(),
+        arch: installed_ref.arch().map(|s| s.to_string()).unwrap_or_default(),
+        commit: installed_ref.commit().map(|s| s.to_string()).unwrap_or_default(),
+        origin: installed_ref.origin().map(|s| s.to_string()).unwrap_or_default(),
+        scope,
+        created_at: Utc::now().timestamp(),
+        size: 0,
+    })
+}
+
+fn write_backup(manifest: &BackupManifest, source: &Path, destination: &Path) -> Result<(), InstallerError> {
+    let file = File::create(destination)
+        .map_err(|e| InstallerError::Failed(format!("Failed to create {}: {}", destination.display(), e)))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+
+    let manifest_json = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at.max(0) as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, MANIFEST_NAME, manifest_json.as_slice())
+        .map_err(|e| InstallerError::Failed(format!("Failed to write manifest: {}", e)))?;
+
+    builder
+        .append_dir_all(DATA_PREFIX, source)
+        .map_err(|e| InstallerError::Failed(format!("Failed to archive {}: {}", source.display(), e)))?;
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| InstallerError::Failed(format!("Failed to finish {}: {}", destination.display(), e)))?;
+
+    Ok(())
+}
+
+fn export_backup(app_id: String, destination: PathBuf, scope: InstallationScope, branch: Option<String>, arch: Option<String>) -> Result<BackupManifest, InstallerError> {
+    let mut manifest = describe_app(&app_id, scope, branch.as_deref(), arch.as_deref())?;
+    let source = data_dir(&manifest.app_id)?;
+
+    if !source.is_dir() {
+        return Err(InstallerError::NotFound(format!("{} has no data to back up", manifest.app_id)));
+    }
+    manifest.size = dir_size(&source);
+
+    if let Err(e) = write_backup(&manifest, &source, &destination) {
+        let _ = std::fs::remove_file(&destination);
+        return Err(e);
+    }
+
+    Ok(manifest)
+}
+
+fn read_manifest(path: &Path) -> Result<BackupManifest, InstallerError> {
+    let file = File::open(path).map_err(|e| InstallerError::Failed(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+    for entry in archive.entries().map_err(|e| InstallerError::InvalidInput(format!("Invalid backup archive: {}", e)))? {
+        let mut entry = entry.map_err(|e| InstallerError::InvalidInput(format!("Invalid backup archive: {}", e)))?;
+        if entry.path().is_ok_and(|p| p == Path::new(MANIFEST_NAME)) {
+            let mut content = String::new();
+            entry
+                .read_to_string(&mut content)
+                .map_err(|e| InstallerError::Failed(format!("Failed to read manifest: {}", e)))?;
+            return parse_manifest(&content);
+        }
+    }
+
+    Err(InstallerError::InvalidInput(format!("{} is not a backup: it has no manifest", path.display())))
+}
+
+fn parse_manifest(content: &str) -> Result<BackupManifest, InstallerError> {
+    let manifest: BackupManifest = serde_json::from_str(content)
+        .map_err(|e| InstallerError::InvalidInput(format!("Invalid backup manifest: {}", e)))?;
+
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(InstallerError::InvalidInput(format!("Backup format {} is newer than this version supports", manifest.format_version)));
+    }
+
+    Ok(manifest)
+}
+
+/// Unpacks into a staging directory next to the target and only swaps it in
+/// once the manifest has been checked, so a bad archive never touches the
+/// existing data.
+fn import_backup(app_id: &str, path: &Path, scope: InstallationScope) -> Result<BackupManifest, InstallerError> {
+    let manifest = read_manifest(path)?;
+    if manifest.app_id != app_id {
+        return Err(InstallerError::InvalidInput(format!("Backup is for {}, not {}", manifest.app_id, app_id)));
+    }
+
+    let cancellable = libflatpak::gio::Cancellable::new();
+    locate_installed(scope, RefSpec::parse(app_id, None, None), &cancellable)?;
+    if userdata::is_running(app_id) {
+        return Err(InstallerError::InUse(format!("{} is running; close it before restoring its data", app_id)));
+    }
+
+    let target = data_dir(app_id)?;
+    let parent = target.parent().ok_or_else(|| InstallerError::InvalidInput("Invalid data directory".to_string()))?;
+    std::fs::create_dir_all(parent)
+        .map_err(|e| InstallerError::Failed(format!("Failed to create {}: {}", parent.display(), e)))?;
+
+    let staging = parent.join(format!(".{}.restore", app_id));
+    let previous = parent.join(format!(".{}.previous", app_id));
+    let _ = std::fs::remove_dir_all(&staging);
+
+    let result = (|| {
+        let file = File::open(path).map_err(|e| InstallerError::Failed(format!("Failed to open {}: {}", path.display(), e)))?;
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(&staging)
+            .map_err(|e| InstallerError::Failed(format!("Failed to unpack backup: {}", e)))?;
+
+        let restored = staging.join(DATA_PREFIX);
+        if !restored.is_dir() {
+            return Err(InstallerError::InvalidInput("Backup contains no data directory".to_string()));
+        }
+
+        let _ = std::fs::remove_dir_all(&previous);
+        if target.exists() {
+            std::fs::rename(&target, &previous)
+                .map_err(|e| InstallerError::Failed(format!("Failed to move existing data aside: {}", e)))?;
+        }
+        if let Err(e) = std::fs::rename(&restored, &target) {
+            let _ = std::fs::rename(&previous, &target);
+            return Err(InstallerError::Failed(format!("Failed to restore data: {}", e)));
+        }
+
+        let _ = std::fs::remove_dir_all(&previous);
+        Ok(())
+    })();
+
+    let _ = std::fs::remove_dir_all(&staging);
+    result.map(|_| manifest)
+}
+
+#[tauri::command]
+pub async fn backup_app_data(app_id: String, destination: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<BackupManifest, InstallerError> {
+    let scope = scope.unwrap_or(InstallationScope::Both);
+    tauri::async_runtime::spawn_blocking(move || export_backup(app_id, PathBuf::from(destination), scope, branch, arch))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Backup failed: {}", e)))?
+}
+
+#[tauri::command]
+pub fn inspect_backup(path: String) -> Result<BackupManifest, InstallerError> {
+    read_manifest(Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn restore_app_data(app_id: String, path: String, scope: Option<InstallationScope>) -> Result<BackupManifest, InstallerError> {
+    let scope = scope.unwrap_or(InstallationScope::Both);
+    tauri::async_runtime::spawn_blocking(move || import_backup(&app_id, Path::new(&path), scope))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Restore failed: {}", e)))?
+}
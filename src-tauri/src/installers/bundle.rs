@@ -0,0 +1,104 @@
+use libflatpak::{BundleRef, Transaction, prelude::*};
+use serde::Serialize;
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::operations::OperationGuard;
+use super::queue::{self, QueuedTask};
+use super::flatpak::{InstallationScope, ref_kind_name, setup_progress_handlers, setup_remote_handlers};
+use crate::util::parse_keyfile;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleInfo {
+    pub path: String,
+    pub ref_name: String,
+    pub name: String,
+    pub kind: String,
+    pub arch: String,
+    pub branch: String,
+    pub origin: Option<String>,
+    pub runtime: Option<String>,
+    pub runtime_repo: Option<String>,
+    pub installed_size: u64,
+    pub file_size: u64,
+    pub metadata: Option<String>,
+}
+
+fn bundle_file(path: &str) -> Result<libflatpak::gio::File, InstallerError> {
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    if !std::path::Path::new(path).is_file() {
+        return Err(InstallerError::NotFound(format!("Bundle {} does not exist", path)));
+    }
+    Ok(libflatpak::gio::File::for_path(path))
+}
+
+#[tauri::command]
+pub fn inspect_bundle(path: String) -> Result<BundleInfo, InstallerError> {
+    let file = bundle_file(&path)?;
+    let bundle = BundleRef::new(&file)
+        .map_err(|e| InstallerError::from_glib(&e, "Invalid Flatpak bundle"))?;
+
+    let metadata = bundle.metadata()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+
+    let runtime = metadata.as_deref().and_then(|metadata| {
+        let groups = parse_keyfile(metadata);
+        groups.get("Application")
+            .or_else(|| groups.get("Runtime"))
+            .and_then(|group| group.get("runtime").cloned())
+    });
+
+    let file_size = std::fs::metadata(path.strip_prefix("file://").unwrap_or(&path))
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    Ok(BundleInfo {
+        ref_name: bundle.format_ref().map(|s| s.to_string()).unwrap_or_default(),
+        name: bundle.name().map(|s| s.to_string()).unwrap_or_default(),
+        kind: ref_kind_name(bundle.kind()),
+        arch: bundle.arch().map(|s| s.to_string()).unwrap_or_default(),
+        branch: bundle.branch().map(|s| s.to_string()).unwrap_or_default(),
+        origin: bundle.origin().map(|s| s.to_string()),
+        runtime,
+        runtime_repo: bundle.runtime_repo_url().map(|s| s.to_string()),
+        installed_size: bundle.installed_size(),
+        file_size,
+        metadata,
+        path,
+    })
+}
+
+pub(super) fn run_install_bundle(app: &AppHandle, operation: &OperationGuard, path: &str, scope: InstallationScope) -> Result<(), InstallerError> {
+    let file = bundle_file(path)?;
+    let ref_name = BundleRef::new(&file)
+        .map_err(|e| InstallerError::from_glib(&e, "Invalid Flatpak bundle"))?
+        .format_ref()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| path.to_string());
+
+    let installation = scope.open_single()?;
+
+    let tx = Transaction::for_installation(&installation, Some(operation.cancellable()))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+    setup_remote_handlers(&tx, app.clone());
+
+    tx.add_install_bundle(&file, None)
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to install bundle {}", path)))?;
+
+    app.emit("flatpak-install-started", serde_json::json!({ "ref": ref_name, "operation_id": operation.id(), "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    app.emit("flatpak-install-complete", serde_json::json!({ "ref": ref_name, "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn install_bundle(app: AppHandle, path: String, scope: Option<InstallationScope>) -> Result<(), InstallerError> {
+    bundle_file(&path)?;
+    queue::submit(&app, scope.unwrap_or_default(), QueuedTask::InstallBundle { path }).await
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use libflatpak::{Installation, InstalledRef, Transaction, prelude::*};
+use serde::Serialize;
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, ref_kind_name, setup_progress_handlers};
+use super::operations::OperationGuard;
+use super::queue::{self, QueuedTask};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnusedRef {
+    pub ref_name: String,
+    pub scope: InstallationScope,
+    pub name: String,
+    pub kind: String,
+    pub arch: String,
+    pub branch: String,
+    pub origin: String,
+    pub installed_size: u64,
+}
+
+pub(super) fn unused_refs(installation: &Installation, cancellable: &libflatpak::gio::Cancellable) -> Result<Vec<InstalledRef>, InstallerError> {
+    installation
+        .list_unused_refs(None, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list unused refs"))
+}
+
+pub(super) fn unused_ref_names(installation: &Installation, cancellable: &libflatpak::gio::Cancellable) -> Result<HashSet<String>, InstallerError> {
+    Ok(unused_refs(installation, cancellable)?
+        .iter()
+        .filter_map(|r| r.format_ref().map(|s| s.to_string()))
+        .collect())
+}
+
+/// Uninstalls `refs` in a single transaction and returns the bytes freed.
+pub(super) fn remove_refs(app: &AppHandle, operation: &OperationGuard, installation: &Installation, scope: InstallationScope, refs: &[String]) -> Result<u64, InstallerError> {
+    let cancellable = operation.cancellable();
+
+    let freed: u64 = unused_refs(installation, cancellable)?
+        .iter()
+        .filter(|r| r.format_ref().is_some_and(|s| refs.iter().any(|full_ref| full_ref == s.as_str())))
+        .map(|r| r.installed_size())
+        .sum();
+
+    let tx = Transaction::for_installation(installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+
+    for full_ref in refs {
+        tx.add_uninstall(full_ref)
+            .map_err(|e| InstallerError::from_glib(&e, format!("Failed to uninstall {}", full_ref)))?;
+    }
+
+    app.emit("flatpak-cleanup-started", serde_json::json!({ "refs": refs, "operation_id": operation.id(), "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    app.emit("flatpak-cleanup-complete", serde_json::json!({ "refs": refs, "scope": scope, "freed": freed }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(freed)
+}
+
+/// Removes the given unused refs, or every unused ref when `refs` is `None`.
+/// Refs that are no longer unused by the time the job runs are skipped.
+pub(super) fn run_remove_unused(app: &AppHandle, operation: &OperationGuard, scope: InstallationScope, refs: Option<&[String]>) -> Result<(), InstallerError> {
+    let installation = scope.open_single()?;
+    let unused = unused_ref_names(&installation, operation.cancellable())?;
+
+    let mut targets: Vec<String> = match refs {
+        Some(refs) => refs.iter().filter(|r| unused.contains(*r)).cloned().collect(),
+        None => unused.into_iter().collect(),
+    };
+    targets.sort();
+
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    remove_refs(app, operation, &installation, scope, &targets)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_unused_refs(scope: Option<InstallationScope>) -> Result<Vec<UnusedRef>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut unused = Vec::new();
+
+    for (scope, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        for installed_ref in unused_refs(&installation, &cancellable)? {
+            let Some(ref_name) = installed_ref.format_ref().map(|s| s.to_string()) else {
+                continue;
+            };
+            unused.push(UnusedRef {
+                ref_name,
+                scope,
+                name: installed_ref.name().map(|s| s.to_string()).unwrap_or_default(),
+                kind: ref_kind_name(installed_ref.kind()),
+                arch: installed_ref.arch().map(|s| s.to_string()).unwrap_or_default(),
+                branch: installed_ref.branch().map(|s| s.to_string()).unwrap_or_default(),
+                origin: installed_ref.origin().map(|s| s.to_string()).unwrap_or_default(),
+                installed_size: installed_ref.installed_size(),
+            });
+        }
+    }
+
+    Ok(unused)
+}
+
+/// Without a scope, both installations are cleaned, each in its own queued
+/// job.
+#[tauri::command]
+pub async fn remove_unused_refs(app: AppHandle, scope: Option<InstallationScope>, refs: Option<Vec<String>>) -> Result<(), InstallerError> {
+    match scope.unwrap_or(InstallationScope::Both) {
+        InstallationScope::Both => {
+            let (system, user) = tokio::join!(
+                queue::submit(&app, InstallationScope::System, QueuedTask::RemoveUnused { refs: refs.clone() }),
+                queue::submit(&app, InstallationScope::User, QueuedTask::RemoveUnused { refs })
+            );
+            system?;
+            user
+        }
+        scope => queue::submit(&app, scope, QueuedTask::RemoveUnused { refs }).await,
+    }
+}
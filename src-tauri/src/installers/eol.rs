@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use libflatpak::{Installation, InstalledRef, RemoteRef, Transaction, prelude::*};
+use serde::Serialize;
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_installed, find_ref, locate_installed, resolve_installed_scope, setup_progress_handlers};
+use super::operations::OperationGuard;
+use super::queue::{self, QueuedTask};
+use super::userdata::data_dir;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EolStatus {
+    pub ref_name: String,
+    pub scope: InstallationScope,
+    pub remote: String,
+    pub installed: bool,
+    pub eol: Option<String>,
+    pub eol_rebase: Option<String>,
+}
+
+/// Remote refs keyed by full ref, listed once per remote.
+struct RemoteRefCache<'a> {
+    installation: &'a Installation,
+    cancellable: &'a libflatpak::gio::Cancellable,
+    remotes: HashMap<String, HashMap<String, RemoteRef>>,
+}
+
+impl<'a> RemoteRefCache<'a> {
+    fn new(installation: &'a Installation, cancellable: &'a libflatpak::gio::Cancellable) -> Self {
+        Self { installation, cancellable, remotes: HashMap::new() }
+    }
+
+    fn get(&mut self, remote: &str, full_ref: &str) -> Option<&RemoteRef> {
+        if !self.remotes.contains_key(remote) {
+            let refs = match self.installation.list_remote_refs_sync(remote, Some(self.cancellable)) {
+                Ok(refs) => refs
+                    .into_iter()
+                    .filter_map(|r| Some((r.format_ref()?.to_string(), r)))
+                    .collect(),
+                Err(e) => {
+                    eprintln!("Failed to list refs of remote {}: {}", remote, e);
+                    HashMap::new()
+                }
+            };
+            self.remotes.insert(remote.to_string(), refs);
+        }
+
+        self.remotes.get(remote)?.get(full_ref)
+    }
+}
+
+/// The remote's summary is newer than the deploy data, so prefer its EOL
+/// markers and fall back to what was recorded at install time.
+fn installed_status(installed_ref: &InstalledRef, scope: InstallationScope, cache: &mut RemoteRefCache) -> Option<EolStatus> {
+    let ref_name = installed_ref.format_ref()?.to_string();
+    let origin = installed_ref.origin().map(|s| s.to_string()).unwrap_or_default();
+    let remote_ref = cache.get(&origin, &ref_name);
+
+    Some(EolStatus {
+        eol: remote_ref.and_then(|r| r.eol()).or_else(|| installed_ref.eol()).map(|s| s.to_string()),
+        eol_rebase: remote_ref.and_then(|r| r.eol_rebase()).or_else(|| installed_ref.eol_rebase()).map(|s| s.to_string()),
+        ref_name,
+        scope,
+        remote: origin,
+        installed: true,
+    })
+}
+
+/// Expands an `eol-rebase` value, which may be a bare id, into a full ref
+/// keeping the old kind, arch and branch.
+fn rebase_target(old_ref: &str, eol_rebase: &str) -> String {
+    if eol_rebase.contains('/') {
+        return eol_rebase.to_string();
+    }
+
+    let mut parts: Vec<&str> = old_ref.split('/').collect();
+    if parts.len() == 4 {
+        parts[1] = eol_rebase;
+        parts.join("/")
+    } else {
+        eol_rebase.to_string()
+    }
+}
+
+fn ref_id(full_ref: &str) -> &str {
+    full_ref.split('/').nth(1).unwrap_or(full_ref)
+}
+
+/// Renames `from` to `to`, failing with `AlreadyExists` instead of replacing
+/// an existing `to`.
+fn rename_noreplace(from: &Path, to: &Path) -> std::io::Result<()> {
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    let result = unsafe { libc::renameat2(libc::AT_FDCWD, from.as_ptr(), libc::AT_FDCWD, to.as_ptr(), libc::RENAME_NOREPLACE) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Moves `~/.var/app/<old>` to the new id and leaves a symlink behind, the
+/// same way `flatpak run` migrates renamed apps. Data the replacement already
+/// has is never touched.
+fn migrate_data_dir(old_id: &str, new_id: &str) -> Result<bool, InstallerError> {
+    let old_dir = data_dir(old_id)?;
+    let new_dir = data_dir(new_id)?;
+
+    let old_is_dir = std::fs::symlink_metadata(&old_dir).is_ok_and(|m| m.is_dir());
+    if !old_is_dir || std::fs::symlink_metadata(&new_dir).is_ok() {
+        return Ok(false);
+    }
+
+    match rename_noreplace(&old_dir, &new_dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(InstallerError::Failed(format!("Failed to move {} to {}: {}", old_dir.display(), new_dir.display(), e))),
+    }
+
+    // The data has moved at this point; the link only helps tools that still
+    // look under the old id.
+    if let Err(e) = std::os::unix::fs::symlink(new_id, &old_dir) {
+        eprintln!("Failed to link {}: {}", old_dir.display(), e);
+    }
+
+    Ok(true)
+}
+
+pub(super) fn run_rebase(app: &AppHandle, operation: &OperationGuard, ref_name: &str, spec: RefSpec, scope: InstallationScope) -> Result<(), InstallerError> {
+    let cancellable = operation.cancellable();
+    let (scope, installation) = locate_installed(scope, spec, cancellable)?;
+    let installed_ref = find_installed(&installation, spec, cancellable)?;
+
+    let mut cache = RemoteRefCache::new(&installation, cancellable);
+    let status = installed_status(&installed_ref, scope, &mut cache)
+        .ok_or_else(|| InstallerError::Failed(format!("Failed to read {}", spec.id)))?;
+    let eol_rebase = status.eol_rebase
+        .ok_or_else(|| InstallerError::NotFound(format!("{} has no replacement to migrate to", spec.id)))?;
+    let new_ref = rebase_target(&status.ref_name, &eol_rebase);
+
+    let tx = Transaction::for_installation(&installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+
+    tx.add_rebase(&status.remote, &new_ref, &[], &[spec.id])
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to install {}", new_ref)))?;
+    tx.add_uninstall(&status.ref_name)
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to uninstall {}", status.ref_name)))?;
+
+    app.emit("flatpak-rebase-started", serde_json::json!({
+        "ref": ref_name,
+        "old_ref": status.ref_name,
+        "new_ref": new_ref,
+        "operation_id": operation.id(),
+        "scope": scope
+    }))
+    .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    // The rebase has already happened, so a failed data move is reported
+    // with the result instead of failing the job.
+    let (data_migrated, data_error) = match migrate_data_dir(spec.id, ref_id(&new_ref)) {
+        Ok(migrated) => (migrated, None),
+        Err(e) => {
+            eprintln!("Failed to migrate data from {} to {}: {}", spec.id, ref_id(&new_ref), e);
+            (false, Some(e))
+        }
+    };
+
+    app.emit("flatpak-rebase-complete", serde_json::json!({
+        "ref": ref_name,
+        "old_ref": status.ref_name,
+        "new_ref": new_ref,
+        "scope": scope,
+        "data_migrated": data_migrated,
+        "data_error": data_error
+    }))
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn collect_eol_refs(scope: Option<InstallationScope>) -> Result<Vec<EolStatus>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut statuses = Vec::new();
+
+    for (scope, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        let installed_refs = installation
+            .list_installed_refs(Some(&cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to list installed refs"))?;
+
+        let mut cache = RemoteRefCache::new(&installation, &cancellable);
+        statuses.extend(installed_refs
+            .iter()
+            .filter_map(|r| installed_status(r, scope, &mut cache))
+            .filter(|status| status.eol.is_some() || status.eol_rebase.is_some()));
+    }
+
+    Ok(statuses)
+}
+
+#[tauri::command]
+pub async fn list_eol_refs(scope: Option<InstallationScope>) -> Result<Vec<EolStatus>, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || collect_eol_refs(scope))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to list end-of-life refs: {}", e)))?
+}
+
+fn resolve_eol_status(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>) -> Result<EolStatus, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+    if let Ok((scope, installation)) = locate_installed(scope.unwrap_or(InstallationScope::Both), spec, &cancellable) {
+        let installed_ref = find_installed(&installation, spec, &cancellable)?;
+        let mut cache = RemoteRefCache::new(&installation, &cancellable);
+        return installed_status(&installed_ref, scope, &mut cache)
+            .ok_or_else(|| InstallerError::Failed(format!("Failed to read {}", spec.id)));
+    }
+
+    let scope = match scope.unwrap_or_default() {
+        InstallationScope::Both => InstallationScope::System,
+        scope => scope,
+    };
+    let installation = scope.open_single()?;
+    let (remote_name, full_ref) = find_ref(&installation, remote.as_deref(), spec, &cancellable)?;
+    let mut cache = RemoteRefCache::new(&installation, &cancellable);
+    let remote_ref = cache.get(&remote_name, &full_ref);
+
+    Ok(EolStatus {
+        eol: remote_ref.and_then(|r| r.eol()).map(|s| s.to_string()),
+        eol_rebase: remote_ref.and_then(|r| r.eol_rebase()).map(|s| s.to_string()),
+        ref_name: full_ref,
+        scope,
+        remote: remote_name,
+        installed: false,
+    })
+}
+
+#[tauri::command]
+pub async fn get_eol_status(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>) -> Result<EolStatus, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || resolve_eol_status(ref_name, scope, remote, branch, arch))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read end-of-life status: {}", e)))?
+}
+
+#[tauri::command]
+pub async fn migrate_eol_app(app: AppHandle, ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<(), InstallerError> {
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+    let scope = resolve_installed_scope(scope.unwrap_or(InstallationScope::Both), spec)?;
+    queue::submit(&app, scope, QueuedTask::Rebase { ref_name, branch, arch }).await
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use libflatpak::gio::IOErrorEnum;
+use libflatpak::glib;
+use serde::Serialize;
+
+/// Installer failures, serialized as `{ "code": "...", "message": "..." }` so
+/// the frontend can branch on `code` and show `message`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", content = "message", rename_all = "snake_case")]
+pub enum InstallerError {
+    AlreadyInstalled(String),
+    NotInstalled(String),
+    NotFound(String),
+    OutOfSpace(String),
+    PermissionDenied(String),
+    AuthorizationDenied(String),
+    Network(String),
+    Untrusted(String),
+    InUse(String),
+    NeedsNewerFlatpak(String),
+    InvalidInput(String),
+    Busy(String),
+    Cancelled(String),
+    Declined(String),
+    Failed(String),
+}
+
+impl InstallerError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            InstallerError::AlreadyInstalled(_) => "already_installed",
+            InstallerError::NotInstalled(_) => "not_installed",
+            InstallerError::NotFound(_) => "not_found",
+            InstallerError::OutOfSpace(_) => "out_of_space",
+            InstallerError::PermissionDenied(_) => "permission_denied",
+            InstallerError::AuthorizationDenied(_) => "authorization_denied",
+            InstallerError::Network(_) => "network",
+            InstallerError::Untrusted(_) => "untrusted",
+            InstallerError::InUse(_) => "in_use",
+            InstallerError::NeedsNewerFlatpak(_) => "needs_newer_flatpak",
+            InstallerError::InvalidInput(_) => "invalid_input",
+            InstallerError::Busy(_) => "busy",
+            InstallerError::Cancelled(_) => "cancelled",
+            InstallerError::Declined(_) => "declined",
+            InstallerError::Failed(_) => "failed",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            InstallerError::AlreadyInstalled(message)
+            | InstallerError::NotInstalled(message)
+            | InstallerError::NotFound(message)
+            | InstallerError::OutOfSpace(message)
+            | InstallerError::PermissionDenied(message)
+            | InstallerError::AuthorizationDenied(message)
+            | InstallerError::Network(message)
+            | InstallerError::Untrusted(message)
+            | InstallerError::InUse(message)
+            | InstallerError::NeedsNewerFlatpak(message)
+            | InstallerError::InvalidInput(message)
+            | InstallerError::Busy(message)
+            | InstallerError::Cancelled(message)
+            | InstallerError::Declined(message)
+            | InstallerError::Failed(message) => message,
+        }
+    }
+
+    /// Classifies a glib error by its flatpak or gio domain code, prefixing
+    /// the message with what was being attempted.
+    pub fn from_glib(error: &glib::Error, context: impl fmt::Display) -> Self {
+        classify(error, format!("{}: {}", context, error))
+    }
+}
+
+fn classify(error: &glib::Error, message: String) -> InstallerError {
+    if let Some(code) = error.kind::<libflatpak::Error>() {
+        return match code {
+            libflatpak::Error::AlreadyInstalled => InstallerError::AlreadyInstalled(message),
+            libflatpak::Error::NotInstalled => InstallerError::NotInstalled(message),
+            libflatpak::Error::RemoteNotFound
+            | libflatpak::Error::RuntimeNotFound
+            | libflatpak::Error::RefNotFound
+            | libflatpak::Error::NotCached => InstallerError::NotFound(message),
+            libflatpak::Error::OutOfSpace => InstallerError::OutOfSpace(message),
+            libflatpak::Error::PermissionDenied | libflatpak::Error::WrongUser => InstallerError::PermissionDenied(message),
+            libflatpak::Error::NotAuthorized | libflatpak::Error::AuthenticationFailed => InstallerError::AuthorizationDenied(message),
+            libflatpak::Error::Untrusted => InstallerError::Untrusted(message),
+            libflatpak::Error::RemoteUsed | libflatpak::Error::RuntimeUsed => InstallerError::InUse(message),
+            libflatpak::Error::NeedNewFlatpak => InstallerError::NeedsNewerFlatpak(message),
+            libflatpak::Error::InvalidRef | libflatpak::Error::InvalidName | libflatpak::Error::InvalidData => InstallerError::InvalidInput(message),
+            libflatpak::Error::Aborted => InstallerError::Declined(message),
+            _ => InstallerError::Failed(message),
+        };
+    }
+
+    if let Some(code) = error.kind::<IOErrorEnum>() {
+        match code {
+            IOErrorEnum::Cancelled => return InstallerError::Cancelled(message),
+            IOErrorEnum::NoSpace => return InstallerError::OutOfSpace(message),
+            IOErrorEnum::PermissionDenied => return InstallerError::PermissionDenied(message),
+            IOErrorEnum::NotFound => return InstallerError::NotFound(message),
+            IOErrorEnum::HostNotFound
+            | IOErrorEnum::HostUnreachable
+            | IOErrorEnum::NetworkUnreachable
+            | IOErrorEnum::ConnectionRefused
+            | IOErrorEnum::TimedOut => return InstallerError::Network(message),
+            _ => {}
+        }
+    }
+
+    classify_message(message)
+}
+
+/// Errors from the system helper and from libcurl only reach us as text,
+/// so fall back to recognising their wording.
+fn classify_message(message: String) -> InstallerError {
+    let lower = message.to_lowercase();
+
+    if lower.contains("policykit") || lower.contains("not authorized") || lower.contains("notauthorized") {
+        InstallerError::AuthorizationDenied(message)
+    } else if lower.contains("no space left") {
+        InstallerError::OutOfSpace(message)
+    } else if lower.contains("could not resolve")
+        || lower.contains("couldn't resolve")
+        || lower.contains("couldn't connect")
+        || lower.contains("network is unreachable")
+        || lower.contains("timeout was reached")
+    {
+        InstallerError::Network(message)
+    } else {
+        InstallerError::Failed(message)
+    }
+}
+
+impl fmt::Display for InstallerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for InstallerError {}
+
+impl From<glib::Error> for InstallerError {
+    fn from(error: glib::Error) -> Self {
+        classify(&error, error.to_string())
+    }
+}
+
+/// Helpers that still report plain strings surface as `failed`, unless the
+/// text clearly says otherwise.
+impl From<String> for InstallerError {
+    fn from(message: String) -> Self {
+        classify_message(message)
+    }
+}
+
+impl From<&str> for InstallerError {
+    fn from(message: &str) -> Self {
+        classify_message(message.to_string())
+    }
+}
+
+impl From<InstallerError> for String {
+    fn from(error: InstallerError) -> Self {
+        error.message().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_messages() {
+        let cases = [
+            ("Flatpak system operation Deploy not allowed for user: PolicyKit denied", "authorization_denied"),
+            ("GDBus.Error:org.freedesktop.PolicyKit1.Error.NotAuthorized: Not authorized", "authorization_denied"),
+            ("Error writing to file: No space left on device", "out_of_space"),
+            ("While fetching https://dl.flathub.org/repo/summary: [6] Could not resolve hostname", "network"),
+            ("[7] Couldn't connect to server", "network"),
+            ("[28] Timeout was reached", "network"),
+            ("Something else went wrong", "failed"),
+        ];
+
+        for (message, code) in cases {
+            let error = classify_message(message.to_string());
+            assert_eq!(error.code(), code, "{}", message);
+            assert_eq!(error.message(), message);
+        }
+    }
+
+    #[test]
+    fn classifies_glib_errors_by_domain() {
+        let error = glib::Error::new(libflatpak::Error::RefNotFound, "Nothing matches org.example.App");
+        assert_eq!(
+            InstallerError::from_glib(&error, "Failed to install org.example.App"),
+            InstallerError::NotFound("Failed to install org.example.App: Nothing matches org.example.App".to_string())
+        );
+
+        let error = glib::Error::new(IOErrorEnum::Cancelled, "Operation was cancelled");
+        assert_eq!(InstallerError::from(error).code(), "cancelled");
+
+        // Unknown codes still fall back to the message.
+        let error = glib::Error::new(IOErrorEnum::Failed, "No space left on device");
+        assert_eq!(InstallerError::from(error).code(), "out_of_space");
+    }
+
+    #[test]
+    fn serializes_code_and_message() {
+        let error = InstallerError::InUse("org.example.App is running".to_string());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "code": "in_use", "message": "org.example.App is running" })
+        );
+    }
+}
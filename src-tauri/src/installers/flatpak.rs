@@ -0,0 +1,859 @@
+use libflatpak::{Installation, InstalledRef, Transaction, prelude::*};
+use serde::{Deserialize, Serialize};
+use super::{AppHandle, Emitter};
+use super::{approvals, cleanup, history, progress, userdata};
+use super::error::InstallerError;
+use super::operations::{OperationErrorPolicy, OperationGuard};
+use super::queue::{self, QueuedTask};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallationScope {
+    #[default]
+    System,
+    User,
+    Both,
+}
+
+impl InstallationScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InstallationScope::System => "system",
+            InstallationScope::User => "user",
+            InstallationScope::Both => "both",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "system" => Some(InstallationScope::System),
+            "user" => Some(InstallationScope::User),
+            "both" => Some(InstallationScope::Both),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn open(self) -> Result<Vec<(InstallationScope, Installation)>, InstallerError> {
+        let open_system = || Installation::new_system(None::<&libflatpak::gio::Cancellable>)
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to open system installation"));
+        let open_user = || Installation::new_user(None::<&libflatpak::gio::Cancellable>)
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to open user installation"));
+
+        match self {
+            InstallationScope::System => Ok(vec![(InstallationScope::System, open_system()?)]),
+            InstallationScope::User => Ok(vec![(InstallationScope::User, open_user()?)]),
+            InstallationScope::Both => {
+                let mut installations = Vec::new();
+                match open_system() {
+                    Ok(installation) => installations.push((InstallationScope::System, installation)),
+                    Err(e) => eprintln!("{}", e),
+                }
+                installations.push((InstallationScope::User, open_user()?));
+                Ok(installations)
+            }
+        }
+    }
+
+    pub(crate) fn open_single(self) -> Result<Installation, InstallerError> {
+        if self == InstallationScope::Both {
+            return Err(InstallerError::InvalidInput("An operation target must be either the system or the user installation".to_string()));
+        }
+        self.open()?
+            .pop()
+            .map(|(_, installation)| installation)
+            .ok_or_else(|| InstallerError::NotFound("No installation available".to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlatpakUpdate {
+    pub ref_name: String,
+    pub scope: InstallationScope,
+    pub name: String,
+    pub kind: String,
+    pub arch: String,
+    pub branch: String,
+    pub origin: String,
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+    pub download_size: u64,
+    pub installed_size: u64,
+}
+
+pub(super) fn setup_progress_handlers(tx: &Transaction, app: AppHandle, operation_id: &str) {
+    let tracker = progress::TransactionTracker::new(operation_id);
+    let recorder = history::HistoryRecorder::new(tx, operation_id);
+
+    tx.connect_new_operation({
+        let app = app.clone();
+        let tracker = tracker.clone();
+        let recorder = recorder.clone();
+        move |tx, op, transaction_progress| {
+            recorder.start(tx, op);
+            let operation = tracker.start(tx, op);
+            let started = operation.started();
+            progress::emit_operation_started(&app, started);
+
+            let app = app.clone();
+            let recorder = recorder.clone();
+            transaction_progress.connect_changed(move |p| {
+                let event = operation.update(p);
+                recorder.progress(&event.ref_name, event.bytes_transferred);
+                progress::emit_progress(&app, &event);
+            });
+        }
+    });
+
+    tx.connect_operation_done({
+        let app = app.clone();
+        let tracker = tracker.clone();
+        let recorder = recorder.clone();
+        move |tx, op, commit, result| {
+            recorder.done(op, commit);
+            progress::emit_progress(&app, &tracker.finish(tx, op));
+            progress::emit_operation_done(&app, &tracker.done(tx, op, commit, result));
+        }
+    });
+
+    // Read once so a policy change doesn't affect transactions already running.
+    let policy = OperationErrorPolicy::current();
+    tx.connect_operation_error(move |tx, op, error, details| {
+        let non_fatal = details & libflatpak::TransactionErrorDetails::NON_FATAL.bits() as i32 != 0;
+        let event = tracker.error(tx, op, error, non_fatal, policy.should_continue(non_fatal));
+        eprintln!("Operation on {} failed ({}): {}", event.ref_name, if event.skipped { "skipped" } else { "aborting" }, error);
+        recorder.failed(op, &event.error, event.skipped);
+        progress::emit_operation_error(&app, &event);
+        event.skipped
+    });
+}
+
+/// Accepts remotes that a `.flatpakref` or bundle asks to add; the user has
+/// already confirmed the install that pulls them in.
+pub(super) fn setup_remote_handlers(tx: &Transaction, app: AppHandle) {
+    tx.connect_add_new_remote(move |_tx, _reason, from_id, remote_name, url| {
+        let _ = app.emit("flatpak-remote-added", serde_json::json!({
+            "remote": remote_name,
+            "url": url,
+            "from": from_id
+        }));
+        true
+    });
+}
+
+/// The app id plus an optional arch and branch, taken either from explicit
+/// arguments or from a `id/arch/branch` style ref name.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RefSpec<'a> {
+    pub id: &'a str,
+    pub arch: Option<&'a str>,
+    pub branch: Option<&'a str>,
+}
+
+impl<'a> RefSpec<'a> {
+    pub fn parse(ref_name: &'a str, arch: Option<&'a str>, branch: Option<&'a str>) -> Self {
+        let ref_name = ref_name
+            .strip_prefix("app/")
+            .or_else(|| ref_name.strip_prefix("runtime/"))
+            .unwrap_or(ref_name);
+        let mut parts = ref_name.split('/');
+        let id = parts.next().unwrap_or(ref_name);
+        let parsed_arch = parts.next().filter(|s| !s.is_empty());
+        let parsed_branch = parts.next().filter(|s| !s.is_empty());
+
+        Self {
+            id,
+            arch: arch.or(parsed_arch),
+            branch: branch.or(parsed_branch),
+        }
+    }
+
+    fn matches(&self, installed_ref: &InstalledRef) -> bool {
+        installed_ref.name().is_some_and(|name| name == self.id)
+            && self.arch.is_none_or(|arch| installed_ref.arch().is_some_and(|a| a == arch))
+            && self.branch.is_none_or(|branch| installed_ref.branch().is_some_and(|b| b == branch))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledBranch {
+    pub scope: InstallationScope,
+    pub ref_name: String,
+    pub arch: String,
+    pub branch: String,
+    pub origin: Option<String>,
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteBranch {
+    pub remote: String,
+    pub ref_name: String,
+    pub kind: String,
+    pub arch: String,
+    pub branch: String,
+    pub commit: Option<String>,
+    pub download_size: u64,
+    pub installed_size: u64,
+}
+
+pub(super) fn find_ref(installation: &Installation, remote: Option<&str>, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<(String, String), InstallerError> {
+    let remotes = match remote {
+        Some(remote) => vec![remote.to_string()],
+        None => super::remotes::enabled_remote_names(installation, cancellable)?,
+    };
+    let branch = spec.branch.unwrap_or("stable");
+
+    let mut last_error: Option<libflatpak::glib::Error> = None;
+    for remote_name in remotes {
+        let remote_ref = installation
+            .fetch_remote_ref_sync(&remote_name, libflatpak::RefKind::App, spec.id, spec.arch, Some(branch), Some(cancellable))
+            .or_else(|_| installation.fetch_remote_ref_sync(&remote_name, libflatpak::RefKind::Runtime, spec.id, spec.arch, Some(branch), Some(cancellable)));
+
+        match remote_ref {
+            Ok(remote_ref) => {
+                let full_ref = remote_ref.format_ref()
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| InstallerError::Failed(format!("Failed to format ref: {}", spec.id)))?;
+                return Ok((remote_name, full_ref));
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(match last_error {
+        Some(e) => InstallerError::from_glib(&e, format!("Ref '{}' ({}) not found", spec.id, branch)),
+        None => InstallerError::NotFound(format!("Ref '{}' not found: no enabled remotes", spec.id)),
+    })
+}
+
+pub(super) fn installed_matches(installation: &Installation, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<Vec<InstalledRef>, InstallerError> {
+    let installed_refs = installation.list_installed_refs(Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list installed refs"))?;
+
+    Ok(installed_refs.into_iter().filter(|installed_ref| spec.matches(installed_ref)).collect())
+}
+
+pub(super) fn find_installed(installation: &Installation, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<InstalledRef, InstallerError> {
+    let mut matches = installed_matches(installation, spec, cancellable)?;
+
+    match matches.len() {
+        0 => Err(InstallerError::NotInstalled(format!("Ref '{}' is not installed", spec.id))),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let refs: Vec<String> = matches.iter()
+                .filter_map(|r| r.format_ref().map(|s| s.to_string()))
+                .collect();
+            Err(InstallerError::InvalidInput(format!("Ref '{}' is installed more than once ({}); choose a branch and arch", spec.id, refs.join(", "))))
+        }
+    }
+}
+
+pub(super) fn find_installed_ref(installation: &Installation, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<String, InstallerError> {
+    find_installed(installation, spec, cancellable)?
+        .format_ref()
+        .map(|s| s.to_string())
+        .ok_or_else(|| InstallerError::Failed(format!("Failed to format ref: {}", spec.id)))
+}
+
+pub(crate) fn default_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" => "i386",
+        arch => arch,
+    }
+}
+
+pub(super) fn ref_kind_name(kind: libflatpak::RefKind) -> String {
+    match kind {
+        libflatpak::RefKind::App => "app".to_string(),
+        libflatpak::RefKind::Runtime => "runtime".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn is_ref_installed(installation: &Installation, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<bool, InstallerError> {
+    Ok(!installed_matches(installation, spec, cancellable)?.is_empty())
+}
+
+pub(super) fn locate_installed(scope: InstallationScope, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<(InstallationScope, Installation), InstallerError> {
+    for (found_scope, installation) in scope.open()? {
+        if is_ref_installed(&installation, spec, cancellable)? {
+            return Ok((found_scope, installation));
+        }
+    }
+
+    Err(InstallerError::NotInstalled(format!("Ref '{}' is not installed", spec.id)))
+}
+
+fn describe_update(installation: &Installation, scope: InstallationScope, installed_ref: &InstalledRef, cancellable: &libflatpak::gio::Cancellable) -> Option<FlatpakUpdate> {
+    let ref_name = installed_ref.format_ref()?.to_string();
+    let name = installed_ref.name()?.to_string();
+    let arch = installed_ref.arch().map(|s| s.to_string()).unwrap_or_default();
+    let branch = installed_ref.branch().map(|s| s.to_string()).unwrap_or_default();
+    let origin = installed_ref.origin().map(|s| s.to_string()).unwrap_or_default();
+
+    let remote_ref = installation
+        .fetch_remote_ref_sync(&origin, installed_ref.kind(), &name, Some(&arch), Some(&branch), Some(cancellable))
+        .map_err(|e| eprintln!("Failed to fetch remote ref for {}: {}", ref_name, e))
+        .ok();
+
+    let new_commit = remote_ref.as_ref()
+        .and_then(|r| r.commit())
+        .or_else(|| installed_ref.latest_commit())
+        .map(|s| s.to_string());
+
+    Some(FlatpakUpdate {
+        ref_name,
+        scope,
+        name,
+        kind: ref_kind_name(installed_ref.kind()),
+        arch,
+        branch,
+        origin,
+        old_commit: installed_ref.commit().map(|s| s.to_string()),
+        new_commit,
+        download_size: remote_ref.as_ref().map(|r| r.download_size()).unwrap_or(0),
+        installed_size: remote_ref.as_ref().map(|r| r.installed_size()).unwrap_or(0),
+    })
+}
+
+#[tauri::command]
+pub async fn list_flatpak_updates(scope: Option<InstallationScope>) -> Result<Vec<FlatpakUpdate>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut updates = Vec::new();
+
+    for (scope, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        let updatable_refs = installation
+            .list_installed_refs_for_update(Some(&cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to list updates"))?;
+
+        updates.extend(updatable_refs
+            .iter()
+            .filter_map(|installed_ref| describe_update(&installation, scope, installed_ref, &cancellable)));
+    }
+
+    Ok(updates)
+}
+
+#[tauri::command]
+pub fn is_flatpak_installed(ref_id: String, scope: Option<InstallationScope>, branch: Option<String>) -> Result<bool, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_id, None, branch.as_deref());
+
+    for (_, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        if is_ref_installed(&installation, spec, &cancellable)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[tauri::command]
+pub fn get_installed_scopes(ref_id: String, scope: Option<InstallationScope>) -> Result<Vec<InstallationScope>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_id, None, None);
+    let mut scopes = Vec::new();
+
+    for (found_scope, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        if is_ref_installed(&installation, spec, &cancellable)? {
+            scopes.push(found_scope);
+        }
+    }
+
+    Ok(scopes)
+}
+
+#[tauri::command]
+pub fn get_installed_branches(ref_id: String, scope: Option<InstallationScope>) -> Result<Vec<InstalledBranch>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_id, None, None);
+    let mut branches = Vec::new();
+
+    for (found_scope, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        for installed_ref in installed_matches(&installation, spec, &cancellable)? {
+            branches.push(InstalledBranch {
+                scope: found_scope,
+                ref_name: installed_ref.format_ref().map(|s| s.to_string()).unwrap_or_default(),
+                arch: installed_ref.arch().map(|s| s.to_string()).unwrap_or_default(),
+                branch: installed_ref.branch().map(|s| s.to_string()).unwrap_or_default(),
+                origin: installed_ref.origin().map(|s| s.to_string()),
+                commit: installed_ref.commit().map(|s| s.to_string()),
+            });
+        }
+    }
+
+    Ok(branches)
+}
+
+fn collect_remote_branches(ref_id: String, scope: Option<InstallationScope>, remote: Option<String>) -> Result<Vec<RemoteBranch>, InstallerError> {
+    let installation = scope.unwrap_or_default().open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_id, None, None);
+
+    let remotes = match remote {
+        Some(remote) => vec![remote],
+        None => super::remotes::enabled_remote_names(&installation, &cancellable)?,
+    };
+
+    let mut branches = Vec::new();
+    for remote_name in remotes {
+        let remote_refs = match installation.list_remote_refs_sync(&remote_name, Some(&cancellable)) {
+            Ok(remote_refs) => remote_refs,
+            Err(e) => {
+                eprintln!("Failed to list refs of remote {}: {}", remote_name, e);
+                continue;
+            }
+        };
+
+        branches.extend(remote_refs
+            .iter()
+            .filter(|remote_ref| remote_ref.name().is_some_and(|name| name == spec.id))
+            .map(|remote_ref| RemoteBranch {
+                remote: remote_name.clone(),
+                ref_name: remote_ref.format_ref().map(|s| s.to_string()).unwrap_or_default(),
+                kind: ref_kind_name(remote_ref.kind()),
+                arch: remote_ref.arch().map(|s| s.to_string()).unwrap_or_default(),
+                branch: remote_ref.branch().map(|s| s.to_string()).unwrap_or_default(),
+                commit: remote_ref.commit().map(|s| s.to_string()),
+                download_size: remote_ref.download_size(),
+                installed_size: remote_ref.installed_size(),
+            }));
+    }
+
+    Ok(branches)
+}
+
+#[tauri::command]
+pub async fn list_remote_branches(ref_id: String, scope: Option<InstallationScope>, remote: Option<String>) -> Result<Vec<RemoteBranch>, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || collect_remote_branches(ref_id, scope, remote))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to list remote branches: {}", e)))?
+}
+
+/// Resolves `Both` to the installation that actually holds the ref.
+pub(super) fn resolve_installed_scope(scope: InstallationScope, spec: RefSpec) -> Result<InstallationScope, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    locate_installed(scope, spec, &cancellable).map(|(scope, _)| scope)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedOperation {
+    pub ref_name: String,
+    pub operation_type: String,
+    pub role: String,
+    pub remote: Option<String>,
+    pub commit: Option<String>,
+    pub download_size: u64,
+    pub installed_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallPlan {
+    pub ref_name: String,
+    pub remote: String,
+    pub scope: InstallationScope,
+    pub operations: Vec<PlannedOperation>,
+    pub download_size: u64,
+    pub installed_size: u64,
+}
+
+pub(super) fn operation_type_name(op_type: libflatpak::TransactionOperationType) -> String {
+    match op_type {
+        libflatpak::TransactionOperationType::Install => "install".to_string(),
+        libflatpak::TransactionOperationType::Update => "update".to_string(),
+        libflatpak::TransactionOperationType::InstallBundle => "install_bundle".to_string(),
+        libflatpak::TransactionOperationType::Uninstall => "uninstall".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn operation_role(ref_name: &str, target_ref: &str, app_runtime: Option<&str>) -> String {
+    if ref_name == target_ref {
+        return "app".to_string();
+    }
+
+    let mut parts = ref_name.split('/');
+    let kind = parts.next().unwrap_or_default();
+    let name = parts.next().unwrap_or_default();
+
+    let role = if name.ends_with(".Locale") {
+        "locale"
+    } else if name.ends_with(".Debug") {
+        "debug"
+    } else if name.contains(".GL.") || name.contains(".GL32.") {
+        "gl_extension"
+    } else if app_runtime.is_some_and(|runtime| runtime.split('/').next() == Some(name)) {
+        "runtime"
+    } else if kind == "runtime" {
+        "extension"
+    } else {
+        "app"
+    };
+    role.to_string()
+}
+
+fn resolve_install_plan(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>) -> Result<InstallPlan, InstallerError> {
+    use std::sync::{Arc, Mutex};
+
+    let scope = scope.unwrap_or_default();
+    let installation = scope.open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+    let (remote_name, full_ref) = find_ref(&installation, remote.as_deref(), spec, &cancellable)?;
+
+    let tx = Transaction::for_installation(&installation, Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    tx.add_install(&remote_name, &full_ref, &[])
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to install {}", spec.id)))?;
+
+    let planned: Arc<Mutex<Option<Vec<PlannedOperation>>>> = Arc::new(Mutex::new(None));
+    tx.connect_ready({
+        let planned = planned.clone();
+        let full_ref = full_ref.clone();
+        move |tx| {
+            let operations = tx.operations();
+            let app_runtime = operations.iter()
+                .find(|op| op.get_ref().is_some_and(|r| r == full_ref.as_str()))
+                .and_then(|op| op.metadata())
+                .and_then(|metadata| {
+                    let groups = crate::util::parse_keyfile(&metadata.to_data());
+                    groups.get("Application").and_then(|group| group.get("runtime").cloned())
+                });
+
+            let ops = operations.iter().map(|op| {
+                let op_ref = op.get_ref().map(|s| s.to_string()).unwrap_or_default();
+                PlannedOperation {
+                    role: operation_role(&op_ref, &full_ref, app_runtime.as_deref()),
+                    ref_name: op_ref,
+                    operation_type: operation_type_name(op.operation_type()),
+                    remote: op.remote().map(|s| s.to_string()),
+                    commit: op.commit().map(|s| s.to_string()),
+                    download_size: op.download_size(),
+                    installed_size: op.installed_size(),
+                }
+            }).collect();
+
+            *planned.lock().unwrap() = Some(ops);
+            false
+        }
+    });
+
+    let run_result = tx.run(Some(&cancellable));
+    let operations = planned.lock().unwrap().take();
+
+    let operations = match (operations, run_result) {
+        (Some(operations), _) => operations,
+        (None, Err(e)) => return Err(InstallerError::from_glib(&e, "Failed to resolve transaction")),
+        (None, Ok(())) => Vec::new(),
+    };
+
+    Ok(InstallPlan {
+        ref_name: full_ref,
+        remote: remote_name,
+        scope,
+        download_size: operations.iter().map(|op| op.download_size).sum(),
+        installed_size: operations.iter().map(|op| op.installed_size).sum(),
+        operations,
+    })
+}
+
+/// Resolves everything installing a ref would pull in without running the
+/// transaction: the `ready` handler records the operations and aborts.
+#[tauri::command]
+pub async fn plan_install(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>) -> Result<InstallPlan, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || resolve_install_plan(ref_name, scope, remote, branch, arch))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to plan install: {}", e)))?
+}
+
+pub(super) fn list_update_refs(scope: InstallationScope) -> Result<Vec<String>, InstallerError> {
+    let installation = scope.open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+
+    let updatable_refs = installation
+        .list_installed_refs_for_update(Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list updates"))?;
+
+    Ok(updatable_refs
+        .iter()
+        .filter_map(|r| r.format_ref().map(|s| s.to_string()))
+        .collect())
+}
+
+pub(super) fn run_install(app: &AppHandle, operation: &OperationGuard, ref_name: &str, spec: RefSpec, scope: InstallationScope, remote: Option<&str>, commit: Option<&str>) -> Result<(), InstallerError> {
+    let installation = scope.open_single()?;
+    let cancellable = operation.cancellable();
+
+    super::remotes::refresh_remotes(&installation, remote, cancellable)?;
+    let (remote_name, full_ref) = find_ref(&installation, remote, spec, cancellable)?;
+
+    let tx = Transaction::for_installation(&installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+    
+    tx.add_install(&remote_name, &full_ref, &[])
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to install {}", spec.id)))?;
+
+    app.emit("flatpak-install-started", serde_json::json!({ "ref": ref_name, "full_ref": full_ref, "operation_id": operation.id(), "scope": scope, "remote": remote_name }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    // libflatpak can't set a commit on an install operation, and an update
+    // can't be queued for a ref that isn't installed yet, so a specific commit
+    // takes a second transaction: the latest commit is briefly deployed, only
+    // the objects that differ are pulled again, and history records both
+    // steps under this operation. Skip it when the latest is what was asked
+    // for.
+    let deployed = installed_matches(&installation, spec, cancellable)?
+        .into_iter()
+        .find(|installed_ref| installed_ref.format_ref().is_some_and(|r| r == full_ref.as_str()))
+        .and_then(|installed_ref| installed_ref.commit().map(|s| s.to_string()));
+    if let Some(commit) = commit.filter(|commit| deployed.as_deref() != Some(*commit)) {
+        let tx = Transaction::for_installation(&installation, Some(cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+        setup_progress_handlers(&tx, app.clone(), operation.id());
+
+        tx.add_update(&full_ref, &[], Some(commit))
+            .map_err(|e| InstallerError::from_glib(&e, format!("Failed to deploy {} at {}", spec.id, commit)))?;
+
+        operation.run(app, &tx)?;
+    }
+
+    app.emit("flatpak-install-complete", serde_json::json!({ "ref": ref_name, "scope": scope, "commit": commit }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+pub(super) fn run_uninstall(app: &AppHandle, operation: &OperationGuard, ref_name: &str, spec: RefSpec, scope: InstallationScope, remove_unused: bool, delete_data: bool) -> Result<(), InstallerError> {
+    let cancellable = operation.cancellable();
+    let (scope, installation) = locate_installed(scope, spec, cancellable)?;
+    
+    let tx = Transaction::for_installation(&installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+
+    let previously_unused = if remove_unused {
+        cleanup::unused_ref_names(&installation, cancellable)?
+    } else {
+        Default::default()
+    };
+
+    let full_ref = find_installed_ref(&installation, spec, cancellable)?;
+
+    // Check before anything is removed, so a running app doesn't end up
+    // uninstalled with its data left behind.
+    if delete_data && userdata::is_running(spec.id) {
+        return Err(InstallerError::InUse(format!("{} is running; close it before removing its data", spec.id)));
+    }
+    
+    tx.add_uninstall(&full_ref)
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to uninstall {}", spec.id)))?;
+
+    app.emit("flatpak-uninstall-started", serde_json::json!({ "ref": ref_name, "operation_id": operation.id(), "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    if let Err(e) = operation.run(app, &tx) {
+        if !cancellable.is_cancelled() {
+            let _ = app.emit("flatpak-uninstall-failed", serde_json::json!({
+                "ref": ref_name,
+                "operation_id": operation.id(),
+                "scope": scope,
+                "error": e.message(),
+                "code": e.code()
+            }));
+        }
+        return Err(e);
+    }
+
+    app.emit("flatpak-uninstall-complete", serde_json::json!({ "ref": ref_name, "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    // Other branches or installations of the app share the same data directory.
+    if delete_data {
+        let any_branch = RefSpec { arch: None, branch: None, ..spec };
+        let still_installed = InstallationScope::Both.open()?
+            .iter()
+            .any(|(_, installation)| is_ref_installed(installation, any_branch, cancellable).unwrap_or(true));
+
+        // The app is already gone at this point, so a failure here is
+        // reported on its own instead of failing the uninstall.
+        if !still_installed {
+            match userdata::remove_data(spec.id) {
+                Ok(freed) => {
+                    app.emit("flatpak-app-data-removed", serde_json::json!({ "app_id": spec.id, "freed": freed }))
+                        .map_err(|e| e.to_string())?;
+                }
+                Err(e) => {
+                    eprintln!("Failed to remove data for {}: {}", spec.id, e);
+                    let _ = app.emit("flatpak-app-data-removal-failed", serde_json::json!({
+                        "app_id": spec.id,
+                        "error": e.message(),
+                        "code": e.code()
+                    }));
+                }
+            }
+        }
+    }
+
+    if remove_unused {
+        let mut orphaned: Vec<String> = cleanup::unused_ref_names(&installation, cancellable)?
+            .difference(&previously_unused)
+            .cloned()
+            .collect();
+        orphaned.sort();
+
+        if !orphaned.is_empty() {
+            cleanup::remove_refs(app, operation, &installation, scope, &orphaned)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn run_update(app: &AppHandle, operation: &OperationGuard, ref_name: &str, spec: RefSpec, scope: InstallationScope, commit: Option<&str>) -> Result<(), InstallerError> {
+    let cancellable = operation.cancellable();
+    let (scope, installation) = locate_installed(scope, spec, cancellable)?;
+
+    let tx = Transaction::for_installation(&installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+
+    let installed_ref = find_installed(&installation, spec, cancellable)?;
+    let full_ref = installed_ref.format_ref()
+        .map(|s| s.to_string())
+        .ok_or_else(|| InstallerError::Failed(format!("Failed to format ref: {}", spec.id)))?;
+
+    // The remote summary only describes the latest commit, so an explicit
+    // commit is reviewed once the transaction has resolved it.
+    match commit {
+        Some(_) => approvals::approve_on_ready(&tx, app, operation, &installed_ref)?,
+        None => {
+            if !approvals::approve_update(app, operation, &installation, &installed_ref)? {
+                return Err(InstallerError::Declined(format!("Update of {} was declined: it requests permission changes", spec.id)));
+            }
+        }
+    }
+
+    tx.add_update(&full_ref, &[], commit)
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to update {}", spec.id)))?;
+
+    app.emit("flatpak-update-started", serde_json::json!({ "ref": ref_name, "operation_id": operation.id(), "scope": scope, "commit": commit }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    app.emit("flatpak-update-complete", serde_json::json!({ "ref": ref_name, "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+pub(super) fn run_update_all(app: &AppHandle, operation: &OperationGuard, scope: InstallationScope) -> Result<(), InstallerError> {
+    let installation = scope.open_single()?;
+    let cancellable = operation.cancellable();
+
+    let updatable_refs = installation
+        .list_installed_refs_for_update(Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list updates"))?;
+
+    let mut refs = Vec::new();
+    for installed_ref in &updatable_refs {
+        if approvals::approve_update(app, operation, &installation, installed_ref)? {
+            if let Some(full_ref) = installed_ref.format_ref() {
+                refs.push(full_ref.to_string());
+            }
+        }
+    }
+    if refs.is_empty() {
+        return Ok(());
+    }
+
+    let tx = Transaction::for_installation(&installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+
+    for full_ref in &refs {
+        tx.add_update(full_ref, &[], None)
+            .map_err(|e| InstallerError::from_glib(&e, format!("Failed to update {}", full_ref)))?;
+    }
+
+    app.emit("flatpak-update-started", serde_json::json!({ "refs": refs, "operation_id": operation.id(), "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    app.emit("flatpak-update-complete", serde_json::json!({ "refs": refs, "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn install_flatpak(app: AppHandle, ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>, commit: Option<String>) -> Result<(), InstallerError> {
+    let scope = scope.unwrap_or_default();
+    queue::submit(&app, scope, QueuedTask::Install { ref_name, remote, branch, arch, commit }).await
+}
+
+#[tauri::command]
+pub async fn uninstall_flatpak(app: AppHandle, ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>, remove_unused: Option<bool>, delete_data: Option<bool>) -> Result<(), InstallerError> {
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+    let scope = resolve_installed_scope(scope.unwrap_or(InstallationScope::Both), spec)?;
+    let remove_unused = remove_unused.unwrap_or(false);
+    let delete_data = delete_data.unwrap_or(false);
+    queue::submit(&app, scope, QueuedTask::Uninstall { ref_name, branch, arch, remove_unused, delete_data }).await
+}
+
+#[tauri::command]
+pub async fn update_flatpak(app: AppHandle, ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>, commit: Option<String>) -> Result<(), InstallerError> {
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+    let scope = resolve_installed_scope(scope.unwrap_or(InstallationScope::Both), spec)?;
+    queue::submit(&app, scope, QueuedTask::Update { ref_name, branch, arch, commit }).await
+}
+
+#[tauri::command]
+pub async fn update_all(app: AppHandle, scope: Option<InstallationScope>) -> Result<Vec<String>, InstallerError> {
+    let scope = scope.unwrap_or_default();
+    let refs = list_update_refs(scope)?;
+    if refs.is_empty() {
+        return Ok(refs);
+    }
+
+    queue::submit(&app, scope, QueuedTask::UpdateAll).await?;
+    Ok(refs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts<'a>(spec: RefSpec<'a>) -> (&'a str, Option<&'a str>, Option<&'a str>) {
+        (spec.id, spec.arch, spec.branch)
+    }
+
+    #[test]
+    fn parses_ref_names() {
+        assert_eq!(parts(RefSpec::parse("org.example.App", None, None)), ("org.example.App", None, None));
+        assert_eq!(parts(RefSpec::parse("org.example.App/x86_64/stable", None, None)), ("org.example.App", Some("x86_64"), Some("stable")));
+        assert_eq!(parts(RefSpec::parse("app/org.example.App/aarch64/beta", None, None)), ("org.example.App", Some("aarch64"), Some("beta")));
+        assert_eq!(parts(RefSpec::parse("runtime/org.gnome.Platform/x86_64/46", None, None)), ("org.gnome.Platform", Some("x86_64"), Some("46")));
+        assert_eq!(parts(RefSpec::parse("org.example.App//stable", None, None)), ("org.example.App", None, Some("stable")));
+    }
+
+    #[test]
+    fn explicit_arguments_win() {
+        assert_eq!(
+            parts(RefSpec::parse("org.example.App/x86_64/stable", Some("aarch64"), Some("beta"))),
+            ("org.example.App", Some("aarch64"), Some("beta"))
+        );
+        assert_eq!(parts(RefSpec::parse("org.example.App/x86_64", None, Some("beta"))), ("org.example.App", Some("x86_64"), Some("beta")));
+    }
+}
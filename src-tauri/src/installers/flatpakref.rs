@@ -0,0 +1,144 @@
+use libflatpak::{Installation, Transaction, prelude::*};
+use serde::Serialize;
+use super::{AppHandle, Emitter};
+use super::approvals;
+use super::error::InstallerError;
+use super::operations::OperationGuard;
+use super::queue::{self, QueuedTask};
+use super::flatpak::{InstallationScope, default_arch, setup_progress_handlers, setup_remote_handlers};
+use crate::util::parse_keyfile;
+
+const FLATPAKREF_GROUP: &str = "Flatpak Ref";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlatpakRefInfo {
+    pub path: String,
+    pub name: String,
+    pub branch: String,
+    pub kind: String,
+    pub ref_name: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub comment: Option<String>,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub icon: Option<String>,
+    pub suggested_remote_name: Option<String>,
+    pub runtime_repo: Option<String>,
+    pub existing_remote: Option<String>,
+    pub already_installed: bool,
+}
+
+fn read_flatpakref(path: &str) -> Result<String, InstallerError> {
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    std::fs::read_to_string(path).map_err(|e| InstallerError::Failed(format!("Failed to read {}: {}", path, e)))
+}
+
+fn same_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+fn parse_flatpakref(path: &str, content: &str) -> Result<FlatpakRefInfo, InstallerError> {
+    let groups = parse_keyfile(content);
+    let group = groups
+        .get(FLATPAKREF_GROUP)
+        .ok_or_else(|| InstallerError::InvalidInput(format!("Not a .flatpakref file: missing [{}] group", FLATPAKREF_GROUP)))?;
+
+    let field = |key: &str| group.get(key).filter(|v| !v.is_empty()).cloned();
+
+    let name = field("Name").ok_or_else(|| InstallerError::InvalidInput("Invalid .flatpakref file: missing Name".to_string()))?;
+    let url = field("Url").ok_or_else(|| InstallerError::InvalidInput("Invalid .flatpakref file: missing Url".to_string()))?;
+    let branch = field("Branch").unwrap_or_else(|| "master".to_string());
+    let kind = if field("IsRuntime").is_some_and(|v| v == "true") { "runtime" } else { "app" };
+
+    Ok(FlatpakRefInfo {
+        path: path.to_string(),
+        ref_name: format!("{}/{}/{}/{}", kind, name, default_arch(), branch),
+        name,
+        branch,
+        kind: kind.to_string(),
+        url,
+        title: field("Title"),
+        comment: field("Comment"),
+        description: field("Description"),
+        homepage: field("Homepage"),
+        icon: field("Icon"),
+        suggested_remote_name: field("SuggestRemoteName"),
+        runtime_repo: field("RuntimeRepo"),
+        existing_remote: None,
+        already_installed: false,
+    })
+}
+
+/// The configured remote serving `url`, if any.
+fn existing_remote(installation: &Installation, url: &str, cancellable: &libflatpak::gio::Cancellable) -> Result<Option<String>, InstallerError> {
+    let remotes = installation
+        .list_remotes(Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list remotes"))?;
+
+    Ok(remotes
+        .iter()
+        .find(|remote| remote.url().is_some_and(|remote_url| same_url(&remote_url, url)))
+        .and_then(|remote| remote.name().map(|s| s.to_string())))
+}
+
+#[tauri::command]
+pub fn inspect_flatpakref(path: String, scope: Option<InstallationScope>) -> Result<FlatpakRefInfo, InstallerError> {
+    let content = read_flatpakref(&path)?;
+    let mut info = parse_flatpakref(&path, &content)?;
+
+    let installation = scope.unwrap_or_default().open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+
+    info.existing_remote = existing_remote(&installation, &info.url, &cancellable)?;
+
+    let installed_refs = installation
+        .list_installed_refs(Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list installed refs"))?;
+
+    info.already_installed = installed_refs
+        .iter()
+        .any(|installed_ref| installed_ref.name().is_some_and(|name| name == info.name));
+
+    Ok(info)
+}
+
+pub(super) fn run_install_flatpakref(app: &AppHandle, operation: &OperationGuard, path: &str, scope: InstallationScope) -> Result<(), InstallerError> {
+    let content = read_flatpakref(path)?;
+    let info = parse_flatpakref(path, &content)?;
+
+    let installation = scope.open_single()?;
+
+    let tx = Transaction::for_installation(&installation, Some(operation.cancellable()))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+    setup_remote_handlers(&tx, app.clone());
+
+    // Apps from a known remote were planned and reviewed before queueing; a
+    // new remote can only be reviewed once the transaction has resolved it.
+    if existing_remote(&installation, &info.url, operation.cancellable())?.is_none() {
+        approvals::approve_install_on_ready(&tx, app, operation, &info.ref_name);
+    }
+
+    tx.add_install_flatpakref(&libflatpak::glib::Bytes::from_owned(content.into_bytes()))
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to install {}", info.name)))?;
+
+    app.emit("flatpak-install-started", serde_json::json!({ "ref": info.ref_name, "operation_id": operation.id(), "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    app.emit("flatpak-install-complete", serde_json::json!({ "ref": info.ref_name, "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn install_flatpakref(app: AppHandle, path: String, scope: Option<InstallationScope>) -> Result<(), InstallerError> {
+    let content = read_flatpakref(&path)?;
+    parse_flatpakref(&path, &content)?;
+
+    queue::submit(&app, scope.unwrap_or_default(), QueuedTask::InstallFlatpakref { path }).await
+}
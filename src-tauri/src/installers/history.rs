@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use chrono::Utc;
+use libflatpak::{Transaction, TransactionOperation, prelude::*};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use sqlx::sqlite::SqliteRow;
+use crate::cache::queries::get_db_pool;
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, operation_type_name};
+use super::queue::QueuedOperation;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryOutcome {
+    Success,
+    Failed,
+    /// The operation failed and the transaction carried on without it.
+    Skipped,
+    Cancelled,
+}
+
+impl HistoryOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            HistoryOutcome::Success => "success",
+            HistoryOutcome::Failed => "failed",
+            HistoryOutcome::Skipped => "skipped",
+            HistoryOutcome::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(outcome: &str) -> Self {
+        match outcome {
+            "success" => HistoryOutcome::Success,
+            "skipped" => HistoryOutcome::Skipped,
+            "cancelled" => HistoryOutcome::Cancelled,
+            _ => HistoryOutcome::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub operation_id: String,
+    /// `install`, `update`, `uninstall` or `install_bundle`, or `rebase` for a
+    /// migration that failed before its transaction started.
+    pub action: String,
+    pub ref_name: String,
+    /// Unset for jobs that don't target a single app, such as updating
+    /// everything or installing a bundle.
+    pub app_id: Option<String>,
+    pub scope: InstallationScope,
+    pub from_commit: Option<String>,
+    pub to_commit: Option<String>,
+    pub bytes_downloaded: u64,
+    pub started_at: i64,
+    pub duration_ms: u64,
+    pub outcome: HistoryOutcome,
+    pub error_code: Option<String>,
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    fn from_row(row: &SqliteRow) -> Self {
+        let scope: String = row.get("scope");
+        let outcome: String = row.get("outcome");
+        let bytes_downloaded: i64 = row.get("bytes_downloaded");
+        let duration_ms: i64 = row.get("duration_ms");
+
+        Self {
+            id: row.get("id"),
+            operation_id: row.get("operation_id"),
+            action: row.get("action"),
+            ref_name: row.get("ref_name"),
+            app_id: row.get("app_id"),
+            scope: InstallationScope::parse(&scope).unwrap_or_default(),
+            from_commit: row.get("from_commit"),
+            to_commit: row.get("to_commit"),
+            bytes_downloaded: bytes_downloaded.max(0) as u64,
+            started_at: row.get("started_at"),
+            duration_ms: duration_ms.max(0) as u64,
+            outcome: HistoryOutcome::parse(&outcome),
+            error_code: row.get("error_code"),
+            error: row.get("error"),
+        }
+    }
+
+    fn failure(mut self, outcome: HistoryOutcome, error: &InstallerError) -> Self {
+        self.outcome = outcome;
+        self.error_code = Some(error.code().to_string());
+        self.error = Some(error.message().to_string());
+        self
+    }
+}
+
+/// Every field narrows the results; unset fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryFilter {
+    /// Matches any ref of the app, whatever its arch or branch.
+    pub app_id: Option<String>,
+    pub ref_name: Option<String>,
+    pub action: Option<String>,
+    pub outcome: Option<HistoryOutcome>,
+    pub scope: Option<InstallationScope>,
+    /// Unix timestamps bounding `started_at`, inclusive.
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Number of entries matching the filter, across all pages.
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+async fn insert(entry: &HistoryEntry) -> Result<(), InstallerError> {
+    let pool = get_db_pool().await?;
+
+    sqlx::query("INSERT INTO history (operation_id, action, ref_name, app_id, scope, from_commit, to_commit, bytes_downloaded, started_at, duration_ms, outcome, error_code, error) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+        .bind(&entry.operation_id)
+        .bind(&entry.action)
+        .bind(&entry.ref_name)
+        .bind(&entry.app_id)
+        .bind(entry.scope.as_str())
+        .bind(&entry.from_commit)
+        .bind(&entry.to_commit)
+        .bind(entry.bytes_downloaded as i64)
+        .bind(entry.started_at)
+        .bind(entry.duration_ms as i64)
+        .bind(entry.outcome.as_str())
+        .bind(&entry.error_code)
+        .bind(&entry.error)
+        .execute(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to record history: {}", e)))?;
+
+    Ok(())
+}
+
+/// Transaction callbacks run on the blocking thread driving the transaction,
+/// so entries are written before it moves on.
+fn insert_blocking(entry: HistoryEntry) {
+    if let Err(e) = tauri::async_runtime::block_on(insert(&entry)) {
+        eprintln!("{}", e);
+    }
+}
+
+async fn has_entries(operation_id: &str) -> Result<bool, InstallerError> {
+    let pool = get_db_pool().await?;
+
+    let row = sqlx::query("SELECT 1 FROM history WHERE operation_id = ? LIMIT 1")
+        .bind(operation_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read history: {}", e)))?;
+
+    Ok(row.is_some())
+}
+
+fn job_entry(job: &QueuedOperation, started_at: i64, duration_ms: u64) -> HistoryEntry {
+    HistoryEntry {
+        id: 0,
+        operation_id: job.operation_id.clone(),
+        action: job.task.action().to_string(),
+        ref_name: job.target.clone(),
+        app_id: job.task.app_id(),
+        scope: job.scope,
+        from_commit: None,
+        to_commit: None,
+        bytes_downloaded: 0,
+        started_at,
+        duration_ms,
+        outcome: HistoryOutcome::Cancelled,
+        error_code: None,
+        error: None,
+    }
+}
+
+/// Records a queued job that failed before its transaction ran any operation,
+/// e.g. because the ref could not be found.
+pub(super) async fn record_job_failure(job: &QueuedOperation, started_at: i64, duration_ms: u64, error: &InstallerError) {
+    match has_entries(&job.operation_id).await {
+        Ok(false) => {}
+        Ok(true) => return,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    }
+
+    let outcome = match error {
+        InstallerError::Cancelled(_) => HistoryOutcome::Cancelled,
+        _ => HistoryOutcome::Failed,
+    };
+    if let Err(e) = insert(&job_entry(job, started_at, duration_ms).failure(outcome, error)).await {
+        eprintln!("{}", e);
+    }
+}
+
+/// Records a job removed from the queue before it started.
+pub(super) async fn record_job_removed(job: &QueuedOperation) {
+    if let Err(e) = insert(&job_entry(job, Utc::now().timestamp(), 0)).await {
+        eprintln!("{}", e);
+    }
+}
+
+struct PendingOperation {
+    action: String,
+    from_commit: Option<String>,
+    started_at: i64,
+    started: Instant,
+    bytes_downloaded: u64,
+}
+
+/// Collects one history entry per transaction operation. Operations that
+/// started but never finished when the transaction goes away were cut short,
+/// and are recorded as cancelled.
+pub(super) struct HistoryRecorder {
+    operation_id: String,
+    scope: InstallationScope,
+    pending: Mutex<HashMap<String, PendingOperation>>,
+}
+
+impl HistoryRecorder {
+    pub fn new(tx: &Transaction, operation_id: &str) -> Arc<Self> {
+        let scope = match tx.installation() {
+            Some(installation) if installation.is_user() => InstallationScope::User,
+            _ => InstallationScope::System,
+        };
+
+        Arc::new(Self {
+            operation_id: operation_id.to_string(),
+            scope,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn start(&self, tx: &Transaction, op: &TransactionOperation) {
+        let Some(ref_name) = op.get_ref().map(|s| s.to_string()) else {
+            return;
+        };
+
+        let pending = PendingOperation {
+            action: operation_type_name(op.operation_type()),
+            from_commit: installed_commit(tx, &ref_name),
+            started_at: Utc::now().timestamp(),
+            started: Instant::now(),
+            bytes_downloaded: 0,
+        };
+        self.pending.lock().unwrap().insert(ref_name, pending);
+    }
+
+    pub fn progress(&self, ref_name: &str, bytes_transferred: u64) {
+        if let Some(pending) = self.pending.lock().unwrap().get_mut(ref_name) {
+            pending.bytes_downloaded = bytes_transferred;
+        }
+    }
+
+    pub fn done(&self, op: &TransactionOperation, commit: Option<&str>) {
+        if let Some((ref_name, pending)) = self.take(op) {
+            insert_blocking(self.entry(ref_name, pending, HistoryOutcome::Success, commit.map(str::to_string)));
+        }
+    }
+
+    pub fn failed(&self, op: &TransactionOperation, error: &InstallerError, skipped: bool) {
+        let outcome = match error {
+            InstallerError::Cancelled(_) => HistoryOutcome::Cancelled,
+            _ if skipped => HistoryOutcome::Skipped,
+            _ => HistoryOutcome::Failed,
+        };
+
+        if let Some((ref_name, pending)) = self.take(op) {
+            insert_blocking(self.entry(ref_name, pending, outcome, None).failure(outcome, error));
+        }
+    }
+
+    fn take(&self, op: &TransactionOperation) -> Option<(String, PendingOperation)> {
+        let ref_name = op.get_ref()?.to_string();
+        let pending = self.pending.lock().unwrap().remove(&ref_name)?;
+        Some((ref_name, pending))
+    }
+
+    fn entry(&self, ref_name: String, pending: PendingOperation, outcome: HistoryOutcome, to_commit: Option<String>) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            operation_id: self.operation_id.clone(),
+            action: pending.action,
+            app_id: Some(RefSpec::parse(&ref_name, None, None).id.to_string()),
+            ref_name,
+            scope: self.scope,
+            from_commit: pending.from_commit,
+            to_commit,
+            bytes_downloaded: pending.bytes_downloaded,
+            started_at: pending.started_at,
+            duration_ms: pending.started.elapsed().as_millis() as u64,
+            outcome,
+            error_code: None,
+            error: None,
+        }
+    }
+}
+
+impl Drop for HistoryRecorder {
+    fn drop(&mut self) {
+        let pending: Vec<(String, PendingOperation)> = self.pending.lock().unwrap().drain().collect();
+        for (ref_name, pending) in pending {
+            insert_blocking(self.entry(ref_name, pending, HistoryOutcome::Cancelled, None));
+        }
+    }
+}
+
+/// The commit deployed before the transaction touches `full_ref`, if any.
+fn installed_commit(tx: &Transaction, full_ref: &str) -> Option<String> {
+    let installation = tx.installation()?;
+    let parsed = libflatpak::Ref::parse(full_ref).ok()?;
+    let name = parsed.name()?;
+
+    installation
+        .installed_ref(
+            parsed.kind(),
+            &name,
+            parsed.arch().as_deref(),
+            parsed.branch().as_deref(),
+            None::<&libflatpak::gio::Cancellable>,
+        )
+        .ok()?
+        .commit()
+        .map(|s| s.to_string())
+}
+
+/// Lists recorded operations, newest first.
+#[tauri::command]
+pub async fn list_history(filter: Option<HistoryFilter>, limit: Option<i64>, offset: Option<i64>) -> Result<HistoryPage, InstallerError> {
+    let filter = filter.unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = offset.unwrap_or(0).max(0);
+    let pool = get_db_pool().await?;
+
+    const WHERE: &str = "WHERE (?1 IS NULL OR app_id = ?1) \
+        AND (?2 IS NULL OR ref_name = ?2) \
+        AND (?3 IS NULL OR action = ?3) \
+        AND (?4 IS NULL OR outcome = ?4) \
+        AND (?5 IS NULL OR scope = ?5) \
+        AND (?6 IS NULL OR started_at >= ?6) \
+        AND (?7 IS NULL OR started_at <= ?7)";
+
+    let total: i64 = sqlx::query(&format!("SELECT COUNT(*) AS count FROM history {}", WHERE))
+        .bind(&filter.app_id)
+        .bind(&filter.ref_name)
+        .bind(&filter.action)
+        .bind(filter.outcome.map(HistoryOutcome::as_str))
+        .bind(filter.scope.map(|scope| scope.as_str()))
+        .bind(filter.since)
+        .bind(filter.until)
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to count history: {}", e)))?
+        .get("count");
+
+    let rows = sqlx::query(&format!("SELECT * FROM history {} ORDER BY started_at DESC, id DESC LIMIT ?8 OFFSET ?9", WHERE))
+        .bind(&filter.app_id)
+        .bind(&filter.ref_name)
+        .bind(&filter.action)
+        .bind(filter.outcome.map(HistoryOutcome::as_str))
+        .bind(filter.scope.map(|scope| scope.as_str()))
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read history: {}", e)))?;
+
+    Ok(HistoryPage {
+        entries: rows.iter().map(HistoryEntry::from_row).collect(),
+        total,
+        offset,
+        limit,
+    })
+}
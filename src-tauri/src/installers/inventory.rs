@@ -0,0 +1,117 @@
+use std::collections::{HashMap, HashSet};
+use std::time::UNIX_EPOCH;
+use libflatpak::{Installation, InstalledRef, prelude::*};
+use serde::Serialize;
+use crate::cache::queries::get_apps_batch_opt;
+use crate::cache::types::CachedApp;
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, ref_kind_name};
+use super::pinning::{is_masked, masked_patterns};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledApp {
+    pub ref_name: String,
+    pub id: String,
+    pub kind: String,
+    pub branch: String,
+    pub arch: String,
+    pub origin: String,
+    pub scope: InstallationScope,
+    pub installed_size: u64,
+    pub commit: String,
+    pub version: Option<String>,
+    pub installed_at: Option<i64>,
+    pub update_available: bool,
+    pub masked: bool,
+    pub eol: Option<String>,
+    pub eol_rebase: Option<String>,
+    pub cached: Option<CachedApp>,
+}
+
+/// Refs with a pending update. Falls back to comparing against the latest
+/// commit already pulled when the remotes can't be reached.
+fn pending_updates(installation: &Installation, installed_refs: &[InstalledRef], cancellable: &libflatpak::gio::Cancellable) -> HashSet<String> {
+    match installation.list_installed_refs_for_update(Some(cancellable)) {
+        Ok(refs) => refs.iter().filter_map(|r| r.format_ref().map(|s| s.to_string())).collect(),
+        Err(e) => {
+            eprintln!("Failed to list updates, using local state: {}", e);
+            installed_refs
+                .iter()
+                .filter(|r| r.latest_commit().is_some_and(|latest| Some(latest) != r.commit()))
+                .filter_map(|r| r.format_ref().map(|s| s.to_string()))
+                .collect()
+        }
+    }
+}
+
+/// Flatpak doesn't record install time, so use when the deployment was made.
+fn deploy_time(installed_ref: &InstalledRef) -> Option<i64> {
+    let deploy_dir = installed_ref.deploy_dir()?;
+    let modified = std::fs::metadata(deploy_dir.as_str()).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+fn describe_installed(installed_ref: &InstalledRef, scope: InstallationScope, updates: &HashSet<String>, masked: &[String]) -> Option<InstalledApp> {
+    let ref_name = installed_ref.format_ref()?.to_string();
+
+    Some(InstalledApp {
+        id: installed_ref.name()?.to_string(),
+        kind: ref_kind_name(installed_ref.kind()),
+        branch: installed_ref.branch().map(|s| s.to_string()).unwrap_or_default(),
+        arch: installed_ref.arch().map(|s| s.to_string()).unwrap_or_default(),
+        origin: installed_ref.origin().map(|s| s.to_string()).unwrap_or_default(),
+        scope,
+        installed_size: installed_ref.installed_size(),
+        commit: installed_ref.commit().map(|s| s.to_string()).unwrap_or_default(),
+        version: installed_ref.appdata_version().map(|s| s.to_string()),
+        installed_at: deploy_time(installed_ref),
+        update_available: updates.contains(&ref_name),
+        masked: is_masked(masked, installed_ref),
+        eol: installed_ref.eol().map(|s| s.to_string()),
+        eol_rebase: installed_ref.eol_rebase().map(|s| s.to_string()),
+        cached: None,
+        ref_name,
+    })
+}
+
+fn collect_installed(scope: InstallationScope) -> Result<Vec<InstalledApp>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut apps = Vec::new();
+
+    for (scope, installation) in scope.open()? {
+        let installed_refs = installation
+            .list_installed_refs(Some(&cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to list installed refs"))?;
+        let updates = pending_updates(&installation, &installed_refs, &cancellable);
+        let masked = masked_patterns(&installation, &cancellable);
+
+        apps.extend(installed_refs.iter().filter_map(|r| describe_installed(r, scope, &updates, &masked)));
+    }
+
+    Ok(apps)
+}
+
+#[tauri::command]
+pub async fn list_installed_apps(scope: Option<InstallationScope>) -> Result<Vec<InstalledApp>, InstallerError> {
+    let scope = scope.unwrap_or(InstallationScope::Both);
+    let mut apps = tauri::async_runtime::spawn_blocking(move || collect_installed(scope))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to list installed apps: {}", e)))??;
+
+    let ids: Vec<String> = apps.iter().map(|app| app.id.clone()).collect::<HashSet<_>>().into_iter().collect();
+    let cached: HashMap<String, CachedApp> = get_apps_batch_opt(ids, false, false, false)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load cached app metadata: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .map(|app| (app.app_id.clone(), app))
+        .collect();
+
+    for app in &mut apps {
+        app.cached = cached.get(&app.id).cloned();
+    }
+
+    Ok(apps)
+}
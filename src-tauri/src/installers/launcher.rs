@@ -0,0 +1,127 @@
+use libflatpak::{Instance, LaunchFlags, prelude::*};
+use serde::Serialize;
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_installed, locate_installed};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningInstance {
+    pub instance_id: String,
+    pub app_id: String,
+    pub arch: String,
+    pub branch: String,
+    pub commit: String,
+    pub pid: i32,
+    pub child_pid: i32,
+}
+
+impl RunningInstance {
+    fn from_instance(instance: &Instance) -> Self {
+        Self {
+            instance_id: instance.id().map(|s| s.to_string()).unwrap_or_default(),
+            app_id: instance.app().map(|s| s.to_string()).unwrap_or_default(),
+            arch: instance.arch().map(|s| s.to_string()).unwrap_or_default(),
+            branch: instance.branch().map(|s| s.to_string()).unwrap_or_default(),
+            commit: instance.commit().map(|s| s.to_string()).unwrap_or_default(),
+            pid: instance.pid(),
+            child_pid: instance.child_pid(),
+        }
+    }
+}
+
+fn running_instances() -> Vec<Instance> {
+    Instance::all().into_iter().filter(|instance| instance.is_running()).collect()
+}
+
+fn signal(pid: i32, signal: i32) -> Result<(), InstallerError> {
+    if pid <= 0 {
+        return Err(InstallerError::NotFound(format!("Instance has no process (pid {})", pid)));
+    }
+    if unsafe { libc::kill(pid, signal) } != 0 {
+        return Err(InstallerError::Failed(format!(
+            "Failed to signal process {}: {}",
+            pid,
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+/// Asks the app inside the sandbox to quit, or kills the whole sandbox
+/// when `force` is set, like `flatpak kill` does.
+fn terminate(instance: &Instance, force: bool) -> Result<(), InstallerError> {
+    if force {
+        signal(instance.pid(), libc::SIGKILL)
+    } else {
+        signal(instance.child_pid(), libc::SIGTERM)
+    }
+}
+
+fn launch(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<RunningInstance, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+    let (_, installation) = locate_installed(scope.unwrap_or(InstallationScope::Both), spec, &cancellable)?;
+    let installed_ref = find_installed(&installation, spec, &cancellable)?;
+
+    if installed_ref.kind() != libflatpak::RefKind::App {
+        return Err(InstallerError::InvalidInput(format!("{} is a runtime and cannot be launched", spec.id)));
+    }
+
+    let name = installed_ref.name().map(|s| s.to_string()).unwrap_or_else(|| spec.id.to_string());
+    let installed_arch = installed_ref.arch().map(|s| s.to_string());
+    let installed_branch = installed_ref.branch().map(|s| s.to_string());
+
+    let instance = installation.launch_full(
+        LaunchFlags::NONE,
+        &name,
+        installed_arch.as_deref(),
+        installed_branch.as_deref(),
+        None,
+        Some(&cancellable),
+    )
+    .map_err(|e| InstallerError::from_glib(&e, format!("Failed to launch {}", spec.id)))?;
+
+    Ok(RunningInstance::from_instance(&instance))
+}
+
+#[tauri::command]
+pub async fn launch_app(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<RunningInstance, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || launch(ref_name, scope, branch, arch))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to launch app: {}", e)))?
+}
+
+#[tauri::command]
+pub fn list_running_instances(app_id: Option<String>) -> Result<Vec<RunningInstance>, InstallerError> {
+    Ok(running_instances()
+        .iter()
+        .map(RunningInstance::from_instance)
+        .filter(|instance| app_id.as_ref().is_none_or(|id| &instance.app_id == id))
+        .collect())
+}
+
+#[tauri::command]
+pub fn terminate_instance(instance_id: String, force: Option<bool>) -> Result<(), InstallerError> {
+    let instance = running_instances()
+        .into_iter()
+        .find(|instance| instance.id().is_some_and(|id| id == instance_id.as_str()))
+        .ok_or_else(|| InstallerError::NotFound(format!("Instance {} is not running", instance_id)))?;
+
+    terminate(&instance, force.unwrap_or(false))
+}
+
+/// Terminates every running instance of `app_id` and returns how many
+/// were signalled.
+#[tauri::command]
+pub fn terminate_app(app_id: String, force: Option<bool>) -> Result<usize, InstallerError> {
+    let instances: Vec<Instance> = running_instances()
+        .into_iter()
+        .filter(|instance| instance.app().is_some_and(|id| id == app_id.as_str()))
+        .collect();
+
+    for instance in &instances {
+        terminate(instance, force.unwrap_or(false))?;
+    }
+
+    Ok(instances.len())
+}
@@ -0,0 +1,303 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use libflatpak::{Installation, InstalledRef, Transaction, prelude::*};
+use serde::{Deserialize, Serialize};
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_ref, installed_matches, setup_progress_handlers};
+use super::operations::OperationGuard;
+use super::overrides::{self, Keyfile};
+use super::pinning::{self, is_masked, masked_patterns};
+use super::queue::{self, QueuedTask};
+use super::remotes::refresh_remotes;
+
+const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// A portable list of installed apps, used to set up the same apps on
+/// another machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppsManifest {
+    pub format_version: u32,
+    pub created_at: i64,
+    pub apps: Vec<ManifestApp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestApp {
+    pub id: String,
+    pub branch: String,
+    pub remote: String,
+    pub scope: InstallationScope,
+    /// Set when the app is pinned, so the import deploys and masks the same
+    /// commit.
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// The app's permission override file, by group and key.
+    #[serde(default, skip_serializing_if = "Keyfile::is_empty")]
+    pub overrides: Keyfile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnavailableApp {
+    pub app: ManifestApp,
+    pub error: InstallerError,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverrideFailure {
+    pub app_id: String,
+    pub error: InstallerError,
+}
+
+/// How a manifest compares to what is installed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPlan {
+    pub to_install: Vec<ManifestApp>,
+    pub already_installed: Vec<ManifestApp>,
+    /// Apps whose remote or ref can't be found on this machine.
+    pub unavailable: Vec<UnavailableApp>,
+}
+
+fn describe_app(installation: &Installation, installed_ref: &InstalledRef, scope: InstallationScope, masked: &[String]) -> Result<Option<ManifestApp>, InstallerError> {
+    let Some(id) = installed_ref.name().map(|s| s.to_string()) else {
+        return Ok(None);
+    };
+
+    let commit = if is_masked(masked, installed_ref) {
+        installed_ref.commit().map(|s| s.to_string())
+    } else {
+        None
+    };
+
+    Ok(Some(ManifestApp {
+        branch: installed_ref.branch().map(|s| s.to_string()).unwrap_or_default(),
+        remote: installed_ref.origin().map(|s| s.to_string()).unwrap_or_default(),
+        scope,
+        commit,
+        overrides: overrides::read_override(installation, &id)?,
+        id,
+    }))
+}
+
+fn collect_manifest(scope: InstallationScope) -> Result<AppsManifest, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut apps = Vec::new();
+
+    for (scope, installation) in scope.open()? {
+        let installed_refs = installation
+            .list_installed_refs_by_kind(libflatpak::RefKind::App, Some(&cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to list installed apps"))?;
+        let masked = masked_patterns(&installation, &cancellable);
+
+        for installed_ref in &installed_refs {
+            apps.extend(describe_app(&installation, installed_ref, scope, &masked)?);
+        }
+    }
+
+    Ok(AppsManifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        created_at: Utc::now().timestamp(),
+        apps,
+    })
+}
+
+fn write_manifest(manifest: &AppsManifest, destination: &Path) -> Result<(), InstallerError> {
+    let content = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| InstallerError::Failed(format!("Failed to serialize manifest: {}", e)))?;
+
+    std::fs::write(destination, content)
+        .map_err(|e| InstallerError::Failed(format!("Failed to write {}: {}", destination.display(), e)))
+}
+
+fn read_manifest(path: &Path) -> Result<AppsManifest, InstallerError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| InstallerError::Failed(format!("Failed to read {}: {}", path.display(), e)))?;
+    let manifest: AppsManifest = serde_json::from_str(&content)
+        .map_err(|e| InstallerError::InvalidInput(format!("Invalid apps manifest: {}", e)))?;
+
+    if manifest.format_version > MANIFEST_FORMAT_VERSION {
+        return Err(InstallerError::InvalidInput(format!("Manifest format {} is newer than this version supports", manifest.format_version)));
+    }
+
+    if let Some(app) = manifest.apps.iter().find(|app| app.scope == InstallationScope::Both) {
+        return Err(InstallerError::InvalidInput(format!("{} must target either the system or the user installation", app.id)));
+    }
+
+    Ok(manifest)
+}
+
+fn plan_import(manifest: AppsManifest) -> Result<ImportPlan, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut plan = ImportPlan {
+        to_install: Vec::new(),
+        already_installed: Vec::new(),
+        unavailable: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+
+    for app in manifest.apps {
+        if !seen.insert((app.id.clone(), app.branch.clone(), app.scope)) {
+            continue;
+        }
+
+        let installation = app.scope.open_single()?;
+        let spec = RefSpec::parse(&app.id, None, Some(app.branch.as_str()));
+
+        if !installed_matches(&installation, spec, &cancellable)?.is_empty() {
+            plan.already_installed.push(app);
+            continue;
+        }
+
+        match find_ref(&installation, Some(app.remote.as_str()), spec, &cancellable) {
+            Ok(_) => plan.to_install.push(app),
+            Err(error) => plan.unavailable.push(UnavailableApp { app, error }),
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Installs every app in one transaction, then deploys and masks pinned
+/// commits and restores overrides the installation doesn't already have.
+pub(super) fn run_import(app: &AppHandle, operation: &OperationGuard, apps: &[ManifestApp], scope: InstallationScope) -> Result<(), InstallerError> {
+    let installation = scope.open_single()?;
+    let cancellable = operation.cancellable();
+
+    // Jobs can wait in the queue for a while, so skip anything installed since
+    // the import was planned.
+    let mut pending: Vec<&ManifestApp> = Vec::new();
+    for entry in apps {
+        let spec = RefSpec::parse(&entry.id, None, Some(entry.branch.as_str()));
+        if installed_matches(&installation, spec, cancellable)?.is_empty() {
+            pending.push(entry);
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let remotes: BTreeSet<&str> = pending.iter().map(|entry| entry.remote.as_str()).collect();
+    for remote in remotes {
+        refresh_remotes(&installation, Some(remote), cancellable)?;
+    }
+
+    let mut targets: Vec<(&ManifestApp, String)> = Vec::new();
+    for entry in pending {
+        let spec = RefSpec::parse(&entry.id, None, Some(entry.branch.as_str()));
+        let (_, full_ref) = find_ref(&installation, Some(entry.remote.as_str()), spec, cancellable)?;
+        targets.push((entry, full_ref));
+    }
+    let full_refs: Vec<&str> = targets.iter().map(|(_, full_ref)| full_ref.as_str()).collect();
+
+    let tx = Transaction::for_installation(&installation, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+    setup_progress_handlers(&tx, app.clone(), operation.id());
+
+    for (entry, full_ref) in &targets {
+        tx.add_install(&entry.remote, full_ref, &[])
+            .map_err(|e| InstallerError::from_glib(&e, format!("Failed to install {}", entry.id)))?;
+    }
+
+    app.emit("flatpak-import-started", serde_json::json!({ "refs": full_refs, "operation_id": operation.id(), "scope": scope }))
+        .map_err(|e| e.to_string())?;
+
+    operation.run(app, &tx)?;
+
+    // Installs always deploy the latest commit, so move pinned apps to their
+    // commit in a follow-up transaction.
+    let pinned: Vec<(&ManifestApp, &str, &str)> = targets
+        .iter()
+        .filter_map(|(entry, full_ref)| entry.commit.as_deref().map(|commit| (*entry, full_ref.as_str(), commit)))
+        .collect();
+
+    if !pinned.is_empty() {
+        let tx = Transaction::for_installation(&installation, Some(cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to create transaction"))?;
+
+        setup_progress_handlers(&tx, app.clone(), operation.id());
+
+        for (entry, full_ref, commit) in &pinned {
+            tx.add_update(full_ref, &[], Some(*commit))
+                .map_err(|e| InstallerError::from_glib(&e, format!("Failed to deploy {} at {}", entry.id, commit)))?;
+        }
+
+        operation.run(app, &tx)?;
+
+        for (entry, _, _) in &pinned {
+            pinning::set_masked(&entry.id, Some(scope), Some(entry.branch.as_str()), None, true)?;
+        }
+    }
+
+    // The apps are installed by now, so a failed override (e.g. a dismissed
+    // polkit prompt for the system installation) is reported per app rather
+    // than failing the import.
+    let mut override_failures = Vec::new();
+    for (entry, _) in targets.iter().filter(|(entry, _)| !entry.overrides.is_empty()) {
+        let result = overrides::read_override(&installation, &entry.id).and_then(|existing| {
+            if existing.is_empty() {
+                overrides::write_override(&installation, &entry.id, &entry.overrides)
+            } else {
+                Ok(())
+            }
+        });
+
+        if let Err(error) = result {
+            eprintln!("Failed to restore overrides for {}: {}", entry.id, error);
+            let failure = OverrideFailure { app_id: entry.id.clone(), error };
+            let _ = app.emit("flatpak-import-override-failed", &failure);
+            override_failures.push(failure);
+        }
+    }
+
+    app.emit("flatpak-import-complete", serde_json::json!({ "refs": full_refs, "scope": scope, "override_failures": override_failures }))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn export_installed_apps(destination: String, scope: Option<InstallationScope>) -> Result<AppsManifest, InstallerError> {
+    let scope = scope.unwrap_or(InstallationScope::Both);
+    tauri::async_runtime::spawn_blocking(move || {
+        let manifest = collect_manifest(scope)?;
+        write_manifest(&manifest, &PathBuf::from(destination))?;
+        Ok::<_, InstallerError>(manifest)
+    })
+    .await
+    .map_err(|e| InstallerError::Failed(format!("Export failed: {}", e)))?
+}
+
+/// Compares a manifest with the current installations without changing
+/// anything.
+#[tauri::command]
+pub async fn inspect_apps_manifest(path: String) -> Result<ImportPlan, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || plan_import(read_manifest(Path::new(&path))?))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to inspect manifest: {}", e)))?
+}
+
+/// Installs the apps of a manifest that are missing here. Each installation
+/// gets one queued job, so system and user apps install side by side.
+#[tauri::command]
+pub async fn import_apps_manifest(app: AppHandle, path: String) -> Result<ImportPlan, InstallerError> {
+    let plan = inspect_apps_manifest(path).await?;
+
+    let [system, user] = [InstallationScope::System, InstallationScope::User].map(|scope| {
+        let apps: Vec<ManifestApp> = plan.to_install.iter().filter(|entry| entry.scope == scope).cloned().collect();
+        let app = app.clone();
+        async move {
+            if apps.is_empty() {
+                return Ok(());
+            }
+            queue::submit(&app, scope, QueuedTask::ImportApps { apps }).await
+        }
+    });
+
+    let (system, user) = tokio::join!(system, user);
+    system?;
+    user?;
+
+    Ok(plan)
+}
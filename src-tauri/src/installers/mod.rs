@@ -0,0 +1,23 @@
+pub mod approvals;
+pub mod backup;
+pub mod bundle;
+pub mod cleanup;
+pub mod eol;
+pub mod error;
+pub mod flatpak;
+pub mod flatpakref;
+pub mod history;
+pub mod inventory;
+pub mod launcher;
+pub mod manifest;
+pub mod operations;
+pub mod overrides;
+pub mod permissions;
+pub mod pinning;
+pub mod progress;
+pub mod queue;
+pub mod remotes;
+pub mod settings;
+pub mod userdata;
+
+use tauri::{AppHandle, Emitter};
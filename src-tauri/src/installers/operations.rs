@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use libflatpak::{Transaction, gio::Cancellable, prelude::*};
+use serde::{Deserialize, Serialize};
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::settings;
+
+static OPERATIONS: OnceLock<Mutex<HashMap<String, Cancellable>>> = OnceLock::new();
+static ERROR_POLICY: Mutex<OperationErrorPolicy> = Mutex::new(OperationErrorPolicy::ContinueNonFatal);
+
+fn operations() -> &'static Mutex<HashMap<String, Cancellable>> {
+    OPERATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// What a transaction does when one of its operations fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationErrorPolicy {
+    /// Stop at the first failed operation.
+    Abort,
+    /// Skip failures flatpak marks as non-fatal and stop on anything else,
+    /// like `flatpak install` does.
+    #[default]
+    ContinueNonFatal,
+    /// Skip every failed operation and carry on with the rest.
+    Continue,
+}
+
+impl OperationErrorPolicy {
+    pub fn current() -> Self {
+        *ERROR_POLICY.lock().unwrap()
+    }
+
+    pub(super) fn set_current(self) {
+        *ERROR_POLICY.lock().unwrap() = self;
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OperationErrorPolicy::Abort => "abort",
+            OperationErrorPolicy::ContinueNonFatal => "continue_non_fatal",
+            OperationErrorPolicy::Continue => "continue",
+        }
+    }
+
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy {
+            "abort" => Some(OperationErrorPolicy::Abort),
+            "continue_non_fatal" => Some(OperationErrorPolicy::ContinueNonFatal),
+            "continue" => Some(OperationErrorPolicy::Continue),
+            _ => None,
+        }
+    }
+
+    pub fn should_continue(self, non_fatal: bool) -> bool {
+        match self {
+            OperationErrorPolicy::Abort => false,
+            OperationErrorPolicy::ContinueNonFatal => non_fatal,
+            OperationErrorPolicy::Continue => true,
+        }
+    }
+}
+
+/// Keeps a running operation's cancellable registered until it is dropped.
+pub struct OperationGuard {
+    id: String,
+    cancellable: Cancellable,
+}
+
+impl OperationGuard {
+    pub fn register(id: &str) -> Result<Self, InstallerError> {
+        let mut running = operations().lock().unwrap();
+        if running.contains_key(id) {
+            return Err(InstallerError::Busy(format!("An operation for '{}' is already running", id)));
+        }
+
+        let cancellable = Cancellable::new();
+        running.insert(id.to_string(), cancellable.clone());
+
+        Ok(Self { id: id.to_string(), cancellable })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn cancellable(&self) -> &Cancellable {
+        &self.cancellable
+    }
+
+    /// Runs the transaction, turning a cancelled run into a
+    /// `flatpak-operation-cancelled` event instead of a failure.
+    pub fn run(&self, app: &AppHandle, tx: &Transaction) -> Result<(), InstallerError> {
+        tx.run(Some(&self.cancellable)).map_err(|e| {
+            if self.cancellable.is_cancelled() {
+                let _ = app.emit("flatpak-operation-cancelled", serde_json::json!({ "operation_id": self.id }));
+                InstallerError::Cancelled(format!("Operation '{}' was cancelled", self.id))
+            } else {
+                InstallerError::from_glib(&e, "Transaction failed")
+            }
+        })
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        operations().lock().unwrap().remove(&self.id);
+    }
+}
+
+#[tauri::command]
+pub fn cancel_operation(operation_id: String) -> Result<bool, InstallerError> {
+    let running = operations().lock().unwrap();
+    match running.get(&operation_id) {
+        Some(cancellable) => {
+            cancellable.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub fn list_running_operations() -> Result<Vec<String>, InstallerError> {
+    Ok(operations().lock().unwrap().keys().cloned().collect())
+}
+
+#[tauri::command]
+pub fn get_operation_error_policy() -> Result<OperationErrorPolicy, InstallerError> {
+    Ok(OperationErrorPolicy::current())
+}
+
+#[tauri::command]
+pub async fn set_operation_error_policy(policy: OperationErrorPolicy) -> Result<(), InstallerError> {
+    settings::store_setting(settings::OPERATION_ERROR_POLICY_KEY, policy.as_str()).await?;
+    policy.set_current();
+    Ok(())
+}
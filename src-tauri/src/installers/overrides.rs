@@ -0,0 +1,652 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use libflatpak::{Installation, gio::prelude::FileExt, prelude::*};
+use serde::{Deserialize, Serialize};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, installed_matches, locate_installed};
+use super::permissions::{
+    CONTEXT_GROUP, CONTEXT_KEYS, ENVIRONMENT_GROUP, SESSION_BUS_GROUP, SYSTEM_BUS_GROUP,
+    SandboxPermissions, load_installed_metadata, parse_permissions, permissions_from_keyfile,
+};
+use crate::util::{format_keyfile, parse_keyfile};
+
+pub(super) type Keyfile = BTreeMap<String, BTreeMap<String, String>>;
+
+const GLOBAL_OVERRIDE: &str = "global";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Permission {
+    Context { key: String, value: String },
+    SessionBus { name: String, policy: Option<String> },
+    SystemBus { name: String, policy: Option<String> },
+    Environment { name: String, value: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppPermissions {
+    pub app_id: String,
+    pub scope: InstallationScope,
+    pub base: SandboxPermissions,
+    pub overrides: SandboxPermissions,
+    pub effective: SandboxPermissions,
+    pub risky: Vec<String>,
+}
+
+fn overrides_dir(installation: &Installation) -> Result<PathBuf, InstallerError> {
+    installation
+        .path()
+        .path()
+        .map(|path| path.join("overrides"))
+        .ok_or_else(|| InstallerError::NotFound("Installation has no local path".to_string()))
+}
+
+fn validate_app_id(app_id: &str) -> Result<(), InstallerError> {
+    if app_id == GLOBAL_OVERRIDE || validate_dbus_name(app_id, false).is_ok() {
+        Ok(())
+    } else {
+        Err(InstallerError::InvalidInput(format!("Invalid application id '{}'", app_id)))
+    }
+}
+
+fn override_path(installation: &Installation, app_id: &str) -> Result<PathBuf, InstallerError> {
+    validate_app_id(app_id)?;
+    Ok(overrides_dir(installation)?.join(app_id))
+}
+
+pub(super) fn read_override(installation: &Installation, app_id: &str) -> Result<Keyfile, InstallerError> {
+    let path = override_path(installation, app_id)?;
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(parse_keyfile(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Keyfile::new()),
+        Err(e) => Err(InstallerError::Failed(format!("Failed to read {}: {}", path.display(), e))),
+    }
+}
+
+/// Replaces an app's override. User overrides are written directly; the
+/// system overrides directory is root-owned, so those go through
+/// `flatpak override` run with pkexec.
+pub(super) fn write_override(installation: &Installation, app_id: &str, keyfile: &Keyfile) -> Result<(), InstallerError> {
+    validate_keyfile(keyfile)?;
+
+    if !installation.is_user() {
+        validate_app_id(app_id)?;
+        return write_system_override(app_id, keyfile);
+    }
+
+    let keyfile: Keyfile = keyfile
+        .iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .map(|(group, entries)| (group.clone(), entries.clone()))
+        .collect();
+    let path = override_path(installation, app_id)?;
+    let content = format_keyfile(&keyfile);
+
+    if content.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(InstallerError::Failed(format!("Failed to remove {}: {}", path.display(), e))),
+        };
+    }
+
+    if parse_keyfile(&content) != keyfile {
+        return Err(InstallerError::InvalidInput("Refusing to write an override that does not round-trip".to_string()));
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| InstallerError::Failed(format!("Failed to create {}: {}", parent.display(), e)))?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| InstallerError::Failed(format!("Failed to write {}: {}", tmp_path.display(), e)))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| InstallerError::Failed(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// `flatpak override` options that recreate `keyfile` on top of a reset
+/// override.
+fn override_args(keyfile: &Keyfile) -> Result<Vec<String>, InstallerError> {
+    let mut args = Vec::new();
+
+    for (group, entries) in keyfile {
+        for (key, value) in entries {
+            match group.as_str() {
+                CONTEXT_GROUP => {
+                    let (grant, revoke) = match key.as_str() {
+                        "shared" => ("--share", Some("--unshare")),
+                        "sockets" => ("--socket", Some("--nosocket")),
+                        "devices" => ("--device", Some("--nodevice")),
+                        "features" => ("--allow", Some("--disallow")),
+                        "filesystems" => ("--filesystem", Some("--nofilesystem")),
+                        "persistent" => ("--persist", None),
+                        _ => return Err(InstallerError::InvalidInput(format!("Context key '{}' can't be set through flatpak override", key))),
+                    };
+                    for entry in value.split(';').filter(|entry| !entry.is_empty()) {
+                        match (entry.strip_prefix('!'), revoke) {
+                            (Some(entry), Some(revoke)) => args.push(format!("{}={}", revoke, entry)),
+                            (Some(_), None) => return Err(InstallerError::InvalidInput(format!("'{}' can't be revoked through flatpak override", entry))),
+                            (None, _) => args.push(format!("{}={}", grant, entry)),
+                        }
+                    }
+                }
+                SESSION_BUS_GROUP | SYSTEM_BUS_GROUP => {
+                    let prefix = if group == SYSTEM_BUS_GROUP { "--system-" } else { "--" };
+                    let option = match value.as_str() {
+                        "talk" => "talk-name",
+                        "own" => "own-name",
+                        "none" => "no-talk-name",
+                        _ => return Err(InstallerError::InvalidInput(format!("Bus policy '{}' for {} can't be set through flatpak override", value, key))),
+                    };
+                    args.push(format!("{}{}={}", prefix, option, key));
+                }
+                ENVIRONMENT_GROUP => args.push(format!("--env={}={}", key, value)),
+                _ => return Err(InstallerError::InvalidInput(format!("Override group [{}] can't be set through flatpak override", group))),
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Resets the system override and applies `keyfile` in a single pkexec call,
+/// so polkit only asks once.
+fn write_system_override(app_id: &str, keyfile: &Keyfile) -> Result<(), InstallerError> {
+    let args = override_args(keyfile)?;
+    // The id has been validated as a D-Bus name, so it is safe to inline; the
+    // options are passed as positional arguments.
+    let target = if app_id == GLOBAL_OVERRIDE { "" } else { app_id };
+    let mut script = format!("flatpak override --system --reset {}", target);
+    if !args.is_empty() {
+        script.push_str(&format!(" && exec flatpak override --system \"$@\" {}", target));
+    }
+
+    let output = std::process::Command::new("pkexec")
+        .arg("sh")
+        .arg("-c")
+        .arg(&script)
+        .arg("sh")
+        .args(&args)
+        .output()
+        .map_err(|e| InstallerError::Failed(format!("Failed to run pkexec: {}", e)))?;
+
+    match output.status.code() {
+        Some(0) => Ok(()),
+        // pkexec exits with 126 when the dialog is dismissed and 127 when
+        // authorization fails.
+        Some(126) | Some(127) => Err(InstallerError::AuthorizationDenied(format!("Not authorized to change system overrides for {}", app_id))),
+        _ => Err(InstallerError::Failed(format!(
+            "Failed to write system override for {}: {}",
+            app_id,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+pub(super) fn validate_dbus_name(name: &str, allow_wildcard: bool) -> Result<(), InstallerError> {
+    let name = match name.strip_suffix(".*") {
+        Some(prefix) if allow_wildcard => prefix,
+        _ => name,
+    };
+
+    let elements: Vec<&str> = name.split('.').collect();
+    let valid = name.len() <= 255
+        && elements.len() >= 2
+        && elements.iter().all(|element| {
+            !element.is_empty()
+                && !element.starts_with(|c: char| c.is_ascii_digit())
+                && element.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(InstallerError::InvalidInput(format!("Invalid D-Bus name '{}'", name)))
+    }
+}
+
+fn validate_filesystem(value: &str) -> Result<(), InstallerError> {
+    let (path, mode) = match value.rsplit_once(':') {
+        Some((path, mode)) => (path, Some(mode)),
+        None => (value, None),
+    };
+
+    if let Some(mode) = mode {
+        if !matches!(mode, "ro" | "rw" | "create") {
+            return Err(InstallerError::InvalidInput(format!("Invalid filesystem mode '{}' in '{}'", mode, value)));
+        }
+    }
+
+    let root = path.split('/').next().unwrap_or(path);
+    let valid_root = matches!(root, "host" | "host-os" | "host-etc" | "home" | "~" | "")
+        || root.starts_with("xdg-");
+
+    if path.is_empty() || !valid_root || path.split('/').any(|part| part == "..") {
+        return Err(InstallerError::InvalidInput(format!("Invalid filesystem '{}'", value)));
+    }
+
+    Ok(())
+}
+
+fn validate_context_value(key: &str, value: &str) -> Result<(), InstallerError> {
+    let value = value.strip_prefix('!').unwrap_or(value);
+    let allowed: &[&str] = match key {
+        "shared" => &["network", "ipc"],
+        "sockets" => &["x11", "wayland", "fallback-x11", "pulseaudio", "session-bus", "system-bus", "ssh-auth", "pcsc", "cups", "gpg-agent", "inherit-wayland-socket"],
+        "devices" => &["dri", "usb", "kvm", "shm", "input", "all"],
+        "features" => &["devel", "multiarch", "bluetooth", "canbus", "per-app-dev-shm"],
+        "filesystems" => return validate_filesystem(value),
+        "persistent" => {
+            if value.is_empty() || value.starts_with('/') || value.split('/').any(|part| part == "..") {
+                return Err(InstallerError::InvalidInput(format!("Invalid persistent path '{}'", value)));
+            }
+            return Ok(());
+        }
+        _ => return Err(InstallerError::InvalidInput(format!("Unknown context key '{}'", key))),
+    };
+
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(InstallerError::InvalidInput(format!("Invalid {} value '{}'", key, value)))
+    }
+}
+
+fn validate_env_name(name: &str) -> Result<(), InstallerError> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(InstallerError::InvalidInput(format!("Invalid environment variable name '{}'", name)))
+    }
+}
+
+fn validate_keyfile(keyfile: &Keyfile) -> Result<(), InstallerError> {
+    for (group, entries) in keyfile {
+        if group.contains(['[', ']', '\n']) {
+            return Err(InstallerError::InvalidInput(format!("Invalid group name '{}'", group)));
+        }
+        for (key, value) in entries {
+            if key.contains(['=', '\n', '[']) || value.contains('\n') {
+                return Err(InstallerError::InvalidInput(format!("Invalid entry '{}' in [{}]", key, group)));
+            }
+        }
+
+        match group.as_str() {
+            CONTEXT_GROUP => {
+                for (key, value) in entries {
+                    if CONTEXT_KEYS.contains(&key.as_str()) {
+                        for entry in value.split(';').filter(|entry| !entry.is_empty()) {
+                            validate_context_value(key, entry)?;
+                        }
+                    }
+                }
+            }
+            SESSION_BUS_GROUP | SYSTEM_BUS_GROUP => {
+                for (name, policy) in entries {
+                    validate_dbus_name(name, true)?;
+                    if !matches!(policy.as_str(), "none" | "see" | "talk" | "own") {
+                        return Err(InstallerError::InvalidInput(format!("Invalid bus policy '{}' for {}", policy, name)));
+                    }
+                }
+            }
+            ENVIRONMENT_GROUP => {
+                for name in entries.keys() {
+                    validate_env_name(name)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn context_entries(keyfile: &Keyfile, key: &str) -> Vec<String> {
+    keyfile
+        .get(CONTEXT_GROUP)
+        .and_then(|context| context.get(key))
+        .map(|value| value.split(';').filter(|entry| !entry.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn set_context_entries(keyfile: &mut Keyfile, key: &str, entries: Vec<String>) {
+    let context = keyfile.entry(CONTEXT_GROUP.to_string()).or_default();
+    if entries.is_empty() {
+        context.remove(key);
+    } else {
+        context.insert(key.to_string(), format!("{};", entries.join(";")));
+    }
+}
+
+/// The entry an override stores for a context value, ignoring `:ro`/`:rw`
+/// suffixes so that e.g. `home:ro` replaces an earlier `home`.
+fn context_entry_name(entry: &str) -> &str {
+    let entry = entry.strip_prefix('!').unwrap_or(entry);
+    entry.split(':').next().unwrap_or(entry)
+}
+
+fn bus_group(permission: &Permission) -> Option<&'static str> {
+    match permission {
+        Permission::SessionBus { .. } => Some(SESSION_BUS_GROUP),
+        Permission::SystemBus { .. } => Some(SYSTEM_BUS_GROUP),
+        _ => None,
+    }
+}
+
+enum Change {
+    Grant,
+    Revoke,
+    Reset,
+}
+
+fn apply_change(keyfile: &mut Keyfile, permission: &Permission, change: Change) -> Result<(), InstallerError> {
+    match permission {
+        Permission::Context { key, value } => {
+            validate_context_value(key, value)?;
+            let name = context_entry_name(value);
+            let mut entries: Vec<String> = context_entries(keyfile, key)
+                .into_iter()
+                .filter(|entry| context_entry_name(entry) != name)
+                .collect();
+            match change {
+                Change::Grant => entries.push(value.trim_start_matches('!').to_string()),
+                Change::Revoke => entries.push(format!("!{}", name)),
+                Change::Reset => {}
+            }
+            set_context_entries(keyfile, key, entries);
+        }
+        Permission::SessionBus { name, policy } | Permission::SystemBus { name, policy } => {
+            validate_dbus_name(name, true)?;
+            let group = keyfile.entry(bus_group(permission).unwrap_or_default().to_string()).or_default();
+            match change {
+                Change::Grant => {
+                    group.insert(name.clone(), policy.clone().unwrap_or_else(|| "talk".to_string()));
+                }
+                Change::Revoke => {
+                    group.insert(name.clone(), "none".to_string());
+                }
+                Change::Reset => {
+                    group.remove(name);
+                }
+            }
+        }
+        Permission::Environment { name, value } => {
+            validate_env_name(name)?;
+            let group = keyfile.entry(ENVIRONMENT_GROUP.to_string()).or_default();
+            match change {
+                Change::Grant => {
+                    group.insert(name.clone(), value.clone().unwrap_or_default());
+                }
+                Change::Revoke => {
+                    group.insert(name.clone(), String::new());
+                }
+                Change::Reset => {
+                    group.remove(name);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies an override on top of `permissions` the way flatpak does: plain
+/// context entries are added, `!` entries remove, bus and env keys replace.
+pub(super) fn merge_permissions(permissions: &mut SandboxPermissions, overrides: &SandboxPermissions) {
+    for key in CONTEXT_KEYS {
+        let (Some(list), Some(changes)) = (permissions.context_list_mut(key), overrides.context_list(key)) else {
+            continue;
+        };
+        for entry in changes {
+            let name = context_entry_name(entry);
+            list.retain(|existing| context_entry_name(existing) != name);
+            if !entry.starts_with('!') {
+                list.push(entry.clone());
+            }
+        }
+    }
+
+    permissions.session_bus.extend(overrides.session_bus.clone());
+    permissions.system_bus.extend(overrides.system_bus.clone());
+    permissions.environment.extend(overrides.environment.clone());
+}
+
+/// Overrides default to the user installation, which needs no privileges and
+/// takes precedence over system overrides.
+fn open_scope(scope: Option<InstallationScope>) -> Result<(InstallationScope, Installation), InstallerError> {
+    let scope = scope.unwrap_or(InstallationScope::User);
+    Ok((scope, scope.open_single()?))
+}
+
+fn base_permissions(app_id: &str) -> Result<SandboxPermissions, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(app_id, None, None);
+
+    let (_, installation) = locate_installed(InstallationScope::Both, spec, &cancellable)?;
+    let installed_ref = installed_matches(&installation, spec, &cancellable)?
+        .into_iter()
+        .find(|r| r.kind() == libflatpak::RefKind::App)
+        .ok_or_else(|| InstallerError::NotInstalled(format!("App '{}' is not installed", app_id)))?;
+
+    Ok(parse_permissions(&load_installed_metadata(&installed_ref, &cancellable)?))
+}
+
+/// Layers overrides in the order `flatpak run` applies them: system global,
+/// system per-app, then user global and user per-app.
+fn effective_permissions(app_id: &str, base: &SandboxPermissions) -> Result<SandboxPermissions, InstallerError> {
+    let mut effective = base.clone();
+
+    for (_, installation) in InstallationScope::Both.open()? {
+        for name in [GLOBAL_OVERRIDE, app_id] {
+            let keyfile = read_override(&installation, name)?;
+            merge_permissions(&mut effective, &permissions_from_keyfile(&keyfile));
+        }
+    }
+
+    Ok(effective)
+}
+
+fn app_permissions(app_id: String, scope: InstallationScope, installation: &Installation) -> Result<AppPermissions, InstallerError> {
+    let base = base_permissions(&app_id)?;
+    let overrides = permissions_from_keyfile(&read_override(installation, &app_id)?);
+    let effective = effective_permissions(&app_id, &base)?;
+
+    Ok(AppPermissions {
+        app_id,
+        scope,
+        base,
+        overrides,
+        risky: effective.risky(),
+        effective,
+    })
+}
+
+fn change_permission(app_id: String, scope: Option<InstallationScope>, permission: Permission, change: Change) -> Result<AppPermissions, InstallerError> {
+    let (scope, installation) = open_scope(scope)?;
+
+    let mut keyfile = read_override(&installation, &app_id)?;
+    apply_change(&mut keyfile, &permission, change)?;
+    write_override(&installation, &app_id, &keyfile)?;
+
+    app_permissions(app_id, scope, &installation)
+}
+
+#[tauri::command]
+pub fn get_app_permissions(app_id: String, scope: Option<InstallationScope>) -> Result<AppPermissions, InstallerError> {
+    let (scope, installation) = open_scope(scope)?;
+    app_permissions(app_id, scope, &installation)
+}
+
+/// Writes to the system installation can wait on a polkit prompt, so they
+/// run off the main thread.
+async fn run_change<F>(change: F) -> Result<AppPermissions, InstallerError>
+where
+    F: FnOnce() -> Result<AppPermissions, InstallerError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(change)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to change permissions: {}", e)))?
+}
+
+#[tauri::command]
+pub async fn grant_permission(app_id: String, scope: Option<InstallationScope>, permission: Permission) -> Result<AppPermissions, InstallerError> {
+    run_change(move || change_permission(app_id, scope, permission, Change::Grant)).await
+}
+
+#[tauri::command]
+pub async fn revoke_permission(app_id: String, scope: Option<InstallationScope>, permission: Permission) -> Result<AppPermissions, InstallerError> {
+    run_change(move || change_permission(app_id, scope, permission, Change::Revoke)).await
+}
+
+#[tauri::command]
+pub async fn reset_permission(app_id: String, scope: Option<InstallationScope>, permission: Permission) -> Result<AppPermissions, InstallerError> {
+    run_change(move || change_permission(app_id, scope, permission, Change::Reset)).await
+}
+
+#[tauri::command]
+pub async fn reset_app_permissions(app_id: String, scope: Option<InstallationScope>) -> Result<AppPermissions, InstallerError> {
+    run_change(move || {
+        let (scope, installation) = open_scope(scope)?;
+        write_override(&installation, &app_id, &Keyfile::new())?;
+        app_permissions(app_id, scope, &installation)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyfile(groups: &[(&str, &[(&str, &str)])]) -> Keyfile {
+        groups
+            .iter()
+            .map(|(group, entries)| {
+                let entries = entries.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+                (group.to_string(), entries)
+            })
+            .collect()
+    }
+
+    fn apply(keyfile: &mut Keyfile, permission: Permission, change: Change) {
+        apply_change(keyfile, &permission, change).unwrap();
+    }
+
+    #[test]
+    fn changes_survive_a_write_and_reread() {
+        let mut overrides = Keyfile::new();
+        apply(&mut overrides, Permission::Context { key: "filesystems".into(), value: "home".into() }, Change::Grant);
+        apply(&mut overrides, Permission::Context { key: "filesystems".into(), value: "home:ro".into() }, Change::Grant);
+        apply(&mut overrides, Permission::Context { key: "shared".into(), value: "network".into() }, Change::Revoke);
+        apply(&mut overrides, Permission::SessionBus { name: "org.freedesktop.Notifications".into(), policy: None }, Change::Grant);
+        apply(&mut overrides, Permission::SystemBus { name: "org.freedesktop.login1".into(), policy: None }, Change::Revoke);
+        apply(&mut overrides, Permission::Environment { name: "GTK_THEME".into(), value: Some("Adwaita:dark".into()) }, Change::Grant);
+
+        assert_eq!(
+            overrides,
+            keyfile(&[
+                (CONTEXT_GROUP, &[("filesystems", "home:ro;"), ("shared", "!network;")]),
+                (ENVIRONMENT_GROUP, &[("GTK_THEME", "Adwaita:dark")]),
+                (SESSION_BUS_GROUP, &[("org.freedesktop.Notifications", "talk")]),
+                (SYSTEM_BUS_GROUP, &[("org.freedesktop.login1", "none")]),
+            ])
+        );
+        validate_keyfile(&overrides).unwrap();
+
+        let path = std::env::temp_dir().join(format!("softwarehub-override-test-{}", std::process::id()));
+        std::fs::write(&path, format_keyfile(&overrides)).unwrap();
+        let reread = parse_keyfile(&std::fs::read_to_string(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reread, overrides);
+        validate_keyfile(&reread).unwrap();
+    }
+
+    #[test]
+    fn reset_removes_the_entry() {
+        let mut overrides = keyfile(&[(CONTEXT_GROUP, &[("sockets", "x11;!wayland;")])]);
+        apply(&mut overrides, Permission::Context { key: "sockets".into(), value: "wayland".into() }, Change::Reset);
+        assert_eq!(overrides, keyfile(&[(CONTEXT_GROUP, &[("sockets", "x11;")])]));
+
+        apply(&mut overrides, Permission::Context { key: "sockets".into(), value: "x11".into() }, Change::Reset);
+        assert_eq!(format_keyfile(&overrides), "");
+    }
+
+    #[test]
+    fn rejects_invalid_keys_and_values() {
+        let invalid = [
+            keyfile(&[("Context]\n[Evil", &[("shared", "network;")])]),
+            keyfile(&[(CONTEXT_GROUP, &[("shared=x", "network;")])]),
+            keyfile(&[(CONTEXT_GROUP, &[("shared", "network;\n[Environment]")])]),
+            keyfile(&[(CONTEXT_GROUP, &[("shared", "bluetooth;")])]),
+            keyfile(&[(CONTEXT_GROUP, &[("filesystems", "home/../../etc;")])]),
+            keyfile(&[(CONTEXT_GROUP, &[("filesystems", "/etc:rwx;")])]),
+            keyfile(&[(CONTEXT_GROUP, &[("persistent", "/abs;")])]),
+            keyfile(&[(SESSION_BUS_GROUP, &[("not-a-name", "talk")])]),
+            keyfile(&[(SESSION_BUS_GROUP, &[("org.example.App", "write")])]),
+            keyfile(&[(ENVIRONMENT_GROUP, &[("1PATH", "x")])]),
+        ];
+
+        for overrides in &invalid {
+            assert!(matches!(validate_keyfile(overrides), Err(InstallerError::InvalidInput(_))), "accepted {:?}", overrides);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_changes() {
+        let mut overrides = Keyfile::new();
+        let invalid = [
+            Permission::Context { key: "unknown".into(), value: "x".into() },
+            Permission::Context { key: "devices".into(), value: "gpu".into() },
+            Permission::SessionBus { name: "org".into(), policy: None },
+            Permission::Environment { name: "A-B".into(), value: None },
+        ];
+
+        for permission in &invalid {
+            assert!(apply_change(&mut overrides, permission, Change::Grant).is_err(), "accepted {:?}", permission);
+        }
+        assert!(overrides.values().all(|entries| entries.is_empty()));
+    }
+
+    #[test]
+    fn dbus_names() {
+        assert!(validate_dbus_name("org.example.App", false).is_ok());
+        assert!(validate_dbus_name("org.example_1.App-2", false).is_ok());
+        assert!(validate_dbus_name("org.example.*", true).is_ok());
+        assert!(validate_dbus_name("org.example.*", false).is_err());
+        assert!(validate_dbus_name("org", false).is_err());
+        assert!(validate_dbus_name("org..App", false).is_err());
+        assert!(validate_dbus_name("org.1example.App", false).is_err());
+        assert!(validate_dbus_name("org.example.App; rm -rf", false).is_err());
+        assert!(validate_app_id("global").is_ok());
+    }
+
+    #[test]
+    fn override_args_recreate_the_keyfile() {
+        let overrides = keyfile(&[
+            (CONTEXT_GROUP, &[("filesystems", "home:ro;!host;"), ("persistent", ".mozilla;")]),
+            (ENVIRONMENT_GROUP, &[("GTK_THEME", "Adwaita:dark")]),
+            (SESSION_BUS_GROUP, &[("org.example.Service", "own")]),
+            (SYSTEM_BUS_GROUP, &[("org.freedesktop.login1", "none")]),
+        ]);
+
+        assert_eq!(
+            override_args(&overrides).unwrap(),
+            [
+                "--filesystem=home:ro",
+                "--nofilesystem=host",
+                "--persist=.mozilla",
+                "--env=GTK_THEME=Adwaita:dark",
+                "--own-name=org.example.Service",
+                "--system-no-talk-name=org.freedesktop.login1",
+            ]
+        );
+
+        assert!(override_args(&keyfile(&[(CONTEXT_GROUP, &[("persistent", "!.mozilla;")])])).is_err());
+        assert!(override_args(&keyfile(&[(SESSION_BUS_GROUP, &[("org.example.Service", "see")])])).is_err());
+        assert!(override_args(&keyfile(&[("Extension org.example.Ext", &[("directory", "ext")])])).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use libflatpak::{Ref, prelude::*};
+use serde::{Deserialize, Serialize};
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_ref, installed_matches, locate_installed};
+use crate::util::parse_keyfile;
+
+pub(super) const CONTEXT_GROUP: &str = "Context";
+pub(super) const SESSION_BUS_GROUP: &str = "Session Bus Policy";
+pub(super) const SYSTEM_BUS_GROUP: &str = "System Bus Policy";
+pub(super) const ENVIRONMENT_GROUP: &str = "Environment";
+
+/// The `[Context]` keys, in the order flatpak writes them.
+pub(super) const CONTEXT_KEYS: [&str; 6] = ["shared", "sockets", "devices", "features", "filesystems", "persistent"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SandboxPermissions {
+    pub shared: Vec<String>,
+    pub sockets: Vec<String>,
+    pub devices: Vec<String>,
+    pub features: Vec<String>,
+    pub filesystems: Vec<String>,
+    pub persistent: Vec<String>,
+    pub session_bus: BTreeMap<String, String>,
+    pub system_bus: BTreeMap<String, String>,
+    pub environment: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionReview {
+    pub ref_name: String,
+    pub permissions: SandboxPermissions,
+    pub risky: Vec<String>,
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl SandboxPermissions {
+    pub fn context_list(&self, key: &str) -> Option<&Vec<String>> {
+        match key {
+            "shared" => Some(&self.shared),
+            "sockets" => Some(&self.sockets),
+            "devices" => Some(&self.devices),
+            "features" => Some(&self.features),
+            "filesystems" => Some(&self.filesystems),
+            "persistent" => Some(&self.persistent),
+            _ => None,
+        }
+    }
+
+    pub fn context_list_mut(&mut self, key: &str) -> Option<&mut Vec<String>> {
+        match key {
+            "shared" => Some(&mut self.shared),
+            "sockets" => Some(&mut self.sockets),
+            "devices" => Some(&mut self.devices),
+            "features" => Some(&mut self.features),
+            "filesystems" => Some(&mut self.filesystems),
+            "persistent" => Some(&mut self.persistent),
+            _ => None,
+        }
+    }
+
+    /// Permissions that give the app broad access outside its sandbox,
+    /// formatted like the matching `flatpak run` options.
+    pub fn risky(&self) -> Vec<String> {
+        let mut risky = Vec::new();
+
+        for filesystem in &self.filesystems {
+            let path = filesystem.split(':').next().unwrap_or(filesystem);
+            if matches!(path, "host" | "host-os" | "host-etc" | "home" | "~" | "/") {
+                risky.push(format!("--filesystem={}", filesystem));
+            }
+        }
+        for device in &self.devices {
+            if device == "all" {
+                risky.push(format!("--device={}", device));
+            }
+        }
+        for socket in &self.sockets {
+            if matches!(socket.as_str(), "system-bus" | "session-bus" | "ssh-auth") {
+                risky.push(format!("--socket={}", socket));
+            }
+        }
+        for feature in &self.features {
+            if feature == "devel" {
+                risky.push(format!("--allow={}", feature));
+            }
+        }
+        for (name, policy) in &self.session_bus {
+            if name == "org.freedesktop.Flatpak" && policy != "none" {
+                risky.push(format!("--talk-name={}", name));
+            }
+        }
+
+        risky
+    }
+}
+
+/// Parses the sandbox sections of a flatpak metadata or override keyfile.
+pub fn parse_permissions(metadata: &str) -> SandboxPermissions {
+    permissions_from_keyfile(&parse_keyfile(metadata))
+}
+
+pub(super) fn permissions_from_keyfile(groups: &BTreeMap<String, BTreeMap<String, String>>) -> SandboxPermissions {
+    let mut permissions = SandboxPermissions::default();
+
+    if let Some(context) = groups.get(CONTEXT_GROUP) {
+        for key in CONTEXT_KEYS {
+            if let (Some(value), Some(list)) = (context.get(key), permissions.context_list_mut(key)) {
+                *list = split_list(value);
+            }
+        }
+    }
+    if let Some(policy) = groups.get(SESSION_BUS_GROUP) {
+        permissions.session_bus = policy.clone();
+    }
+    if let Some(policy) = groups.get(SYSTEM_BUS_GROUP) {
+        permissions.system_bus = policy.clone();
+    }
+    if let Some(environment) = groups.get(ENVIRONMENT_GROUP) {
+        permissions.environment = environment.clone();
+    }
+
+    permissions
+}
+
+pub(super) fn fetch_remote_metadata(installation: &libflatpak::Installation, remote: &str, full_ref: &str, cancellable: &libflatpak::gio::Cancellable) -> Result<String, InstallerError> {
+    let parsed_ref = Ref::parse(full_ref)
+        .map_err(|e| InstallerError::from_glib(&e, format!("Invalid ref {}", full_ref)))?;
+
+    let metadata = installation
+        .fetch_remote_metadata_sync(remote, &parsed_ref, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to fetch metadata for {}", full_ref)))?;
+
+    Ok(String::from_utf8_lossy(&metadata).into_owned())
+}
+
+pub(super) fn load_installed_metadata(installed_ref: &libflatpak::InstalledRef, cancellable: &libflatpak::gio::Cancellable) -> Result<String, InstallerError> {
+    let metadata = installed_ref
+        .load_metadata(Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to load installed metadata"))?;
+
+    Ok(String::from_utf8_lossy(&metadata).into_owned())
+}
+
+fn review_remote_permissions(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>) -> Result<PermissionReview, InstallerError> {
+    let installation = scope.unwrap_or_default().open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+    let (remote_name, full_ref) = find_ref(&installation, remote.as_deref(), spec, &cancellable)?;
+    let permissions = parse_permissions(&fetch_remote_metadata(&installation, &remote_name, &full_ref, &cancellable)?);
+
+    Ok(PermissionReview {
+        ref_name: full_ref,
+        risky: permissions.risky(),
+        permissions,
+    })
+}
+
+#[tauri::command]
+pub async fn get_remote_permissions(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>) -> Result<PermissionReview, InstallerError> {
+    tauri::async_runtime::spawn_blocking(move || review_remote_permissions(ref_name, scope, remote, branch, arch))
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read remote permissions: {}", e)))?
+}
+
+#[tauri::command]
+pub fn get_installed_permissions(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<PermissionReview, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+    let (_, installation) = locate_installed(scope.unwrap_or(InstallationScope::Both), spec, &cancellable)?;
+    let installed_ref = installed_matches(&installation, spec, &cancellable)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| InstallerError::NotInstalled(format!("Ref '{}' is not installed", spec.id)))?;
+
+    let permissions = parse_permissions(&load_installed_metadata(&installed_ref, &cancellable)?);
+
+    Ok(PermissionReview {
+        ref_name: installed_ref.format_ref().map(|s| s.to_string()).unwrap_or_default(),
+        risky: permissions.risky(),
+        permissions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_metadata() {
+        let permissions = parse_permissions(
+            "[Application]\n\
+             name=org.example.App\n\
+             \n\
+             [Context]\n\
+             shared=network;\n\
+             sockets=x11;wayland;\n\
+             devices=dri;\n\
+             filesystems=xdg-music:ro; home ;\n\
+             unknown=ignored;\n\
+             \n\
+             [System Bus Policy]\n\
+             org.freedesktop.login1=talk\n\
+             \n\
+             [Environment]\n\
+             LANG=C\n",
+        );
+
+        assert_eq!(permissions.shared, ["network"]);
+        assert_eq!(permissions.sockets, ["x11", "wayland"]);
+        assert_eq!(permissions.devices, ["dri"]);
+        assert_eq!(permissions.filesystems, ["xdg-music:ro", "home"]);
+        assert!(permissions.features.is_empty());
+        assert!(permissions.session_bus.is_empty());
+        assert_eq!(permissions.system_bus.get("org.freedesktop.login1").map(String::as_str), Some("talk"));
+        assert_eq!(permissions.environment.get("LANG").map(String::as_str), Some("C"));
+    }
+
+    #[test]
+    fn flags_risky_permissions() {
+        let permissions = parse_permissions(
+            "[Context]\n\
+             sockets=wayland;session-bus;\n\
+             devices=dri;all;\n\
+             features=devel;\n\
+             filesystems=xdg-download;home:ro;/;host-etc;\n\
+             \n\
+             [Session Bus Policy]\n\
+             org.freedesktop.Flatpak=talk\n\
+             org.freedesktop.Notifications=talk\n",
+        );
+
+        assert_eq!(
+            permissions.risky(),
+            [
+                "--filesystem=home:ro",
+                "--filesystem=/",
+                "--filesystem=host-etc",
+                "--device=all",
+                "--socket=session-bus",
+                "--allow=devel",
+                "--talk-name=org.freedesktop.Flatpak",
+            ]
+        );
+        assert!(parse_permissions("[Context]\nsockets=wayland;\n").risky().is_empty());
+    }
+}
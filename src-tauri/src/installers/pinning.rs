@@ -0,0 +1,273 @@
+use libflatpak::{Installation, InstalledRef, prelude::*};
+use serde::Serialize;
+use super::error::InstallerError;
+use super::flatpak::{InstallationScope, RefSpec, find_installed, find_ref, locate_installed, ref_kind_name};
+
+const MASKED_KEY: &str = "masked";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteCommit {
+    pub commit: String,
+    pub subject: String,
+    pub date: String,
+}
+
+/// Parses `flatpak remote-info --log`, whose output lists the current commit
+/// followed by its history, each entry with `Commit:`, `Subject:` and `Date:`.
+fn parse_commit_log(output: &str) -> Vec<RemoteCommit> {
+    let mut commits: Vec<RemoteCommit> = Vec::new();
+
+    for line in output.lines() {
+        let Some((field, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+
+        match field {
+            "Commit" if !commits.iter().any(|c| c.commit == value) => commits.push(RemoteCommit {
+                commit: value,
+                subject: String::new(),
+                date: String::new(),
+            }),
+            "Subject" => {
+                if let Some(last) = commits.last_mut().filter(|c| c.subject.is_empty()) {
+                    last.subject = value;
+                }
+            }
+            "Date" => {
+                if let Some(last) = commits.last_mut().filter(|c| c.date.is_empty()) {
+                    last.date = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    commits
+}
+
+pub(super) fn masked_patterns(installation: &Installation, cancellable: &libflatpak::gio::Cancellable) -> Vec<String> {
+    installation
+        .config(MASKED_KEY, Some(cancellable))
+        .map(|value| value.split(';').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn save_masked_patterns(installation: &Installation, patterns: &[String], cancellable: &libflatpak::gio::Cancellable) -> Result<(), InstallerError> {
+    let value = patterns.join(";");
+    installation
+        .set_config_sync(MASKED_KEY, &value, Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to update masked refs"))
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == value,
+        Some((prefix, rest)) => {
+            let Some(value) = value.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=value.len())
+                .filter(|&i| value.is_char_boundary(i))
+                .any(|i| glob_matches(rest, &value[i..]))
+        }
+    }
+}
+
+/// Matches a flatpak mask pattern, which is either an id glob or a full
+/// `kind/id/arch/branch` glob.
+fn pattern_matches(pattern: &str, installed_ref: &InstalledRef) -> bool {
+    let Some(full_ref) = installed_ref.format_ref() else {
+        return false;
+    };
+
+    if pattern.contains('/') {
+        let parts: Vec<&str> = pattern.split('/').collect();
+        let ref_parts: Vec<&str> = full_ref.split('/').collect();
+        parts.len() == ref_parts.len() && parts.iter().zip(&ref_parts).all(|(p, r)| glob_matches(p, r))
+    } else {
+        installed_ref.name().is_some_and(|name| glob_matches(pattern, &name))
+    }
+}
+
+pub(super) fn is_masked(patterns: &[String], installed_ref: &InstalledRef) -> bool {
+    patterns.iter().any(|pattern| pattern_matches(pattern, installed_ref))
+}
+
+/// An id-only pattern masks every branch; otherwise mask the matching refs.
+fn mask_pattern(installation: &Installation, spec: RefSpec, cancellable: &libflatpak::gio::Cancellable) -> Result<String, InstallerError> {
+    if spec.arch.is_none() && spec.branch.is_none() {
+        return Ok(spec.id.to_string());
+    }
+
+    let installed_ref = find_installed(installation, spec, cancellable)?;
+    Ok(format!(
+        "{}/{}/{}/{}",
+        ref_kind_name(installed_ref.kind()),
+        spec.id,
+        spec.arch.unwrap_or("*"),
+        spec.branch.unwrap_or("*")
+    ))
+}
+
+pub(super) fn set_masked(ref_name: &str, scope: Option<InstallationScope>, branch: Option<&str>, arch: Option<&str>, masked: bool) -> Result<Vec<String>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let spec = RefSpec::parse(ref_name, arch, branch);
+
+    let (_, installation) = locate_installed(scope.unwrap_or(InstallationScope::Both), spec, &cancellable)?;
+    let pattern = mask_pattern(&installation, spec, &cancellable)?;
+
+    let mut patterns = masked_patterns(&installation, &cancellable);
+    let present = patterns.contains(&pattern);
+
+    if masked && !present {
+        patterns.push(pattern);
+    } else if !masked && present {
+        patterns.retain(|p| p != &pattern);
+    } else {
+        return Ok(patterns);
+    }
+
+    save_masked_patterns(&installation, &patterns, &cancellable)?;
+    Ok(patterns)
+}
+
+/// Lists the commits the remote still has for a ref, newest first. libflatpak
+/// doesn't expose commit history, so this reads it from the flatpak CLI.
+#[tauri::command]
+pub async fn list_remote_commits(ref_name: String, scope: Option<InstallationScope>, remote: Option<String>, branch: Option<String>, arch: Option<String>, limit: Option<usize>) -> Result<Vec<RemoteCommit>, InstallerError> {
+    let (scope, remote_name, full_ref) = tauri::async_runtime::spawn_blocking(move || {
+        let cancellable = libflatpak::gio::Cancellable::new();
+        let spec = RefSpec::parse(&ref_name, arch.as_deref(), branch.as_deref());
+
+        // Prefer the origin of an installed copy so rollbacks come from where
+        // the app was installed from.
+        if let Ok((scope, installation)) = locate_installed(scope.unwrap_or(InstallationScope::Both), spec, &cancellable) {
+            let installed_ref = find_installed(&installation, spec, &cancellable)?;
+            let origin = installed_ref.origin().map(|s| s.to_string()).unwrap_or_default();
+            let full_ref = installed_ref.format_ref().map(|s| s.to_string()).unwrap_or_default();
+            return Ok((scope, remote.unwrap_or(origin), full_ref));
+        }
+
+        let scope = match scope.unwrap_or_default() {
+            InstallationScope::Both => InstallationScope::System,
+            scope => scope,
+        };
+        let installation = scope.open_single()?;
+        let (remote_name, full_ref) = find_ref(&installation, remote.as_deref(), spec, &cancellable)?;
+        Ok::<_, InstallerError>((scope, remote_name, full_ref))
+    })
+    .await
+    .map_err(|e| InstallerError::Failed(format!("Failed to resolve ref: {}", e)))??;
+
+    // Both end up as positional arguments, so they must not pass for options.
+    for value in [&remote_name, &full_ref] {
+        if value.is_empty() || value.starts_with('-') {
+            return Err(InstallerError::InvalidInput(format!("Invalid remote or ref '{}'", value)));
+        }
+    }
+
+    // The log is parsed by its field names, which are only stable untranslated.
+    let output = tokio::process::Command::new("flatpak")
+        .env("LC_ALL", "C")
+        .arg("remote-info")
+        .arg("--log")
+        .arg(format!("--{}", scope.as_str()))
+        .arg("--")
+        .arg(&remote_name)
+        .arg(&full_ref)
+        .output()
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to run flatpak: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(InstallerError::Failed(format!(
+            "Failed to read history of {}: {}",
+            full_ref,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let mut commits = parse_commit_log(&String::from_utf8_lossy(&output.stdout));
+    if let Some(limit) = limit {
+        commits.truncate(limit);
+    }
+
+    Ok(commits)
+}
+
+#[tauri::command]
+pub fn mask_ref(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<Vec<String>, InstallerError> {
+    set_masked(&ref_name, scope, branch.as_deref(), arch.as_deref(), true)
+}
+
+#[tauri::command]
+pub fn unmask_ref(ref_name: String, scope: Option<InstallationScope>, branch: Option<String>, arch: Option<String>) -> Result<Vec<String>, InstallerError> {
+    set_masked(&ref_name, scope, branch.as_deref(), arch.as_deref(), false)
+}
+
+#[tauri::command]
+pub fn list_masked_refs(scope: Option<InstallationScope>) -> Result<Vec<String>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let installation = scope.unwrap_or_default().open_single()?;
+    Ok(masked_patterns(&installation, &cancellable))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REMOTE_INFO_LOG: &str = "
+        ID: org.example.App
+       Ref: app/org.example.App/x86_64/stable
+      Arch: x86_64
+    Branch: stable
+   Version: 1.2
+
+    Commit: 3f1c2a
+    Parent: 9be07d
+   Subject: Update to 1.2 (#42)
+      Date: 2024-05-01 10:00:00 +0000
+
+   History:
+
+    Commit: 3f1c2a
+   Subject: Update to 1.2 (#42)
+      Date: 2024-05-01 10:00:00 +0000
+
+    Commit: 9be07d
+   Subject: Fix: crash on start
+      Date: 2024-04-01 09:30:00 +0000
+";
+
+    #[test]
+    fn parses_remote_info_log() {
+        let commits: Vec<(String, String, String)> = parse_commit_log(REMOTE_INFO_LOG)
+            .into_iter()
+            .map(|c| (c.commit, c.subject, c.date))
+            .collect();
+
+        assert_eq!(
+            commits,
+            [
+                ("3f1c2a".to_string(), "Update to 1.2 (#42)".to_string(), "2024-05-01 10:00:00 +0000".to_string()),
+                ("9be07d".to_string(), "Fix: crash on start".to_string(), "2024-04-01 09:30:00 +0000".to_string()),
+            ]
+        );
+        assert!(parse_commit_log("error: Nothing matches org.example.App").is_empty());
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_matches("org.example.App", "org.example.App"));
+        assert!(!glob_matches("org.example.App", "org.example.App2"));
+        assert!(glob_matches("org.example.*", "org.example.App"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("org.*.App", "org.example.App"));
+        assert!(!glob_matches("org.*.App", "org.example.Tool"));
+        assert!(glob_matches("*.Locale", "org.example.App.Locale"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYd"));
+        assert!(glob_matches("*é*", "café"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::sync::{Arc, Mutex};
+use libflatpak::{Transaction, TransactionOperation, TransactionOperationType, TransactionResult, prelude::*};
+use serde::Serialize;
+use crate::util::RateEstimator;
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::flatpak::operation_type_name;
+
+/// Emitted with an [`OperationStarted`] when the transaction begins one of its
+/// operations.
+pub const OPERATION_STARTED_EVENT: &str = "flatpak-operation-started";
+
+/// Emitted with an [`OperationProgress`] while an operation runs, and once
+/// more with the `done` phase when it finishes.
+pub const PROGRESS_EVENT: &str = "flatpak-progress";
+
+/// Emitted with an [`OperationDone`] for each operation that completes.
+pub const OPERATION_DONE_EVENT: &str = "flatpak-operation-done";
+
+/// Emitted with an [`OperationError`] when an operation fails, whether or not
+/// the transaction carries on without it.
+pub const OPERATION_ERROR_EVENT: &str = "flatpak-operation-error";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressPhase {
+    Estimating,
+    Downloading,
+    Deploying,
+    Removing,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationStarted {
+    pub operation_id: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub operation_type: String,
+    /// Position of this operation in the transaction, starting at 0.
+    pub index: usize,
+    pub total: usize,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub operation_type: String,
+    pub index: usize,
+    pub total: usize,
+    pub phase: ProgressPhase,
+    pub status: String,
+    /// Progress of this operation alone.
+    pub percentage: i32,
+    pub bytes_transferred: u64,
+    pub bytes_total: u64,
+    pub bytes_per_second: f64,
+    pub eta_seconds: Option<u64>,
+    /// Progress of the whole transaction, with operations weighted by their
+    /// download size.
+    pub overall_percentage: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationDone {
+    pub operation_id: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub operation_type: String,
+    pub index: usize,
+    pub total: usize,
+    /// The commit that is now deployed, unset for uninstalls.
+    pub commit: Option<String>,
+    /// Whether the ref was already at that commit.
+    pub no_change: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationError {
+    pub operation_id: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub operation_type: String,
+    pub index: usize,
+    pub total: usize,
+    pub error: InstallerError,
+    /// Flatpak considers the failure safe to skip, e.g. an optional extension
+    /// that is no longer available.
+    pub non_fatal: bool,
+    /// Whether the transaction carries on without this operation.
+    pub skipped: bool,
+}
+
+/// Progress of every operation in one transaction, shared by the handlers of
+/// its operations.
+pub(super) struct TransactionTracker {
+    operation_id: String,
+    /// Weight and percentage of each operation, by index.
+    operations: Mutex<Vec<(u64, i32)>>,
+}
+
+impl TransactionTracker {
+    pub fn new(operation_id: &str) -> Arc<Self> {
+        Arc::new(Self {
+            operation_id: operation_id.to_string(),
+            operations: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The resolved operation list only becomes final once the transaction
+    /// runs, so refresh the weights whenever its length changes.
+    fn locate(&self, tx: &Transaction, op: &TransactionOperation) -> (usize, usize) {
+        let all = tx.operations();
+        let mut operations = self.operations.lock().unwrap();
+        if operations.len() != all.len() {
+            let weights: Vec<u64> = all.iter().map(|o| o.download_size()).collect();
+            let equal = weights.iter().all(|&w| w == 0);
+            let updated: Vec<(u64, i32)> = weights
+                .into_iter()
+                .enumerate()
+                .map(|(i, w)| (if equal { 1 } else { w }, operations.get(i).map_or(0, |o| o.1)))
+                .collect();
+            *operations = updated;
+        }
+
+        let index = all.iter().position(|o| o == op).unwrap_or(0);
+        (index, all.len())
+    }
+
+    fn record(&self, index: usize, percentage: i32) -> i32 {
+        let mut operations = self.operations.lock().unwrap();
+        if let Some(entry) = operations.get_mut(index) {
+            entry.1 = percentage.clamp(0, 100);
+        }
+
+        let total: u64 = operations.iter().map(|o| o.0).sum();
+        if total == 0 {
+            return 0;
+        }
+        let done: f64 = operations.iter().map(|o| o.0 as f64 * o.1 as f64).sum();
+        (done / total as f64).round() as i32
+    }
+
+    pub fn start(self: &Arc<Self>, tx: &Transaction, op: &TransactionOperation) -> OperationTracker {
+        let (index, total) = self.locate(tx, op);
+        OperationTracker {
+            transaction: self.clone(),
+            op_type: op.operation_type(),
+            started: OperationStarted {
+                operation_id: self.operation_id.clone(),
+                ref_name: op.get_ref().map(|s| s.to_string()).unwrap_or_default(),
+                operation_type: operation_type_name(op.operation_type()),
+                index,
+                total,
+                bytes_total: op.download_size(),
+            },
+            rate: Mutex::new(RateEstimator::new()),
+        }
+    }
+
+    pub fn done(&self, tx: &Transaction, op: &TransactionOperation, commit: Option<&str>, result: TransactionResult) -> OperationDone {
+        let (index, total) = self.locate(tx, op);
+        OperationDone {
+            operation_id: self.operation_id.clone(),
+            ref_name: op.get_ref().map(|s| s.to_string()).unwrap_or_default(),
+            operation_type: operation_type_name(op.operation_type()),
+            index,
+            total,
+            commit: commit.map(str::to_string),
+            no_change: result.contains(TransactionResult::NO_CHANGE),
+        }
+    }
+
+    /// A skipped operation counts as finished so the overall percentage can
+    /// still reach 100.
+    pub fn error(&self, tx: &Transaction, op: &TransactionOperation, error: &libflatpak::glib::Error, non_fatal: bool, skipped: bool) -> OperationError {
+        let (index, total) = self.locate(tx, op);
+        let ref_name = op.get_ref().map(|s| s.to_string()).unwrap_or_default();
+        if skipped {
+            self.record(index, 100);
+        }
+
+        OperationError {
+            operation_id: self.operation_id.clone(),
+            operation_type: operation_type_name(op.operation_type()),
+            error: InstallerError::from_glib(error, format!("Failed to {} {}", operation_type_name(op.operation_type()).replace('_', " "), ref_name)),
+            ref_name,
+            index,
+            total,
+            non_fatal,
+            skipped,
+        }
+    }
+
+    /// Builds the final event for an operation the transaction reports as done.
+    pub fn finish(&self, tx: &Transaction, op: &TransactionOperation) -> OperationProgress {
+        let (index, total) = self.locate(tx, op);
+        let ref_name = op.get_ref().map(|s| s.to_string()).unwrap_or_default();
+        let status = match op.operation_type() {
+            TransactionOperationType::Uninstall => format!("Removed {}", ref_name),
+            TransactionOperationType::Update => format!("Updated {}", ref_name),
+            _ => format!("Installed {}", ref_name),
+        };
+
+        OperationProgress {
+            operation_id: self.operation_id.clone(),
+            operation_type: operation_type_name(op.operation_type()),
+            ref_name,
+            index,
+            total,
+            phase: ProgressPhase::Done,
+            status,
+            percentage: 100,
+            bytes_transferred: op.download_size(),
+            bytes_total: op.download_size(),
+            bytes_per_second: 0.0,
+            eta_seconds: Some(0),
+            overall_percentage: self.record(index, 100),
+        }
+    }
+}
+
+/// Tracks one operation of a transaction.
+pub(super) struct OperationTracker {
+    transaction: Arc<TransactionTracker>,
+    op_type: TransactionOperationType,
+    started: OperationStarted,
+    rate: Mutex<RateEstimator>,
+}
+
+impl OperationTracker {
+    pub fn started(&self) -> &OperationStarted {
+        &self.started
+    }
+
+    pub fn update(&self, progress: &libflatpak::TransactionProgress) -> OperationProgress {
+        let percentage = progress.progress();
+        let bytes_transferred = progress.bytes_transferred();
+        let bytes_total = self.started.bytes_total.max(bytes_transferred);
+        let bytes_per_second = self.rate.lock().unwrap().update(bytes_transferred);
+
+        let phase = if self.op_type == TransactionOperationType::Uninstall {
+            ProgressPhase::Removing
+        } else if progress.is_estimating() {
+            ProgressPhase::Estimating
+        } else if bytes_transferred < bytes_total {
+            ProgressPhase::Downloading
+        } else {
+            ProgressPhase::Deploying
+        };
+
+        let eta_seconds = match phase {
+            ProgressPhase::Downloading => self.rate.lock().unwrap().eta(bytes_total - bytes_transferred),
+            _ => None,
+        };
+
+        OperationProgress {
+            operation_id: self.started.operation_id.clone(),
+            ref_name: self.started.ref_name.clone(),
+            operation_type: self.started.operation_type.clone(),
+            index: self.started.index,
+            total: self.started.total,
+            phase,
+            status: progress.status().map(|s| s.to_string()).unwrap_or_default(),
+            percentage,
+            bytes_transferred,
+            bytes_total,
+            bytes_per_second,
+            eta_seconds,
+            overall_percentage: self.transaction.record(self.started.index, percentage),
+        }
+    }
+}
+
+pub(super) fn emit_operation_started(app: &AppHandle, event: &OperationStarted) {
+    let _ = app.emit(OPERATION_STARTED_EVENT, event);
+}
+
+pub(super) fn emit_progress(app: &AppHandle, event: &OperationProgress) {
+    let _ = app.emit(PROGRESS_EVENT, event);
+}
+
+pub(super) fn emit_operation_done(app: &AppHandle, event: &OperationDone) {
+    let _ = app.emit(OPERATION_DONE_EVENT, event);
+}
+
+pub(super) fn emit_operation_error(app: &AppHandle, event: &OperationError) {
+    let _ = app.emit(OPERATION_ERROR_EVENT, event);
+}
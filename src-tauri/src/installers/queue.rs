@@ -0,0 +1,456 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use sqlx::sqlite::SqliteRow;
+use tokio::sync::oneshot;
+use crate::cache::queries::get_db_pool;
+use super::{AppHandle, Emitter};
+use super::error::InstallerError;
+use super::flatpak::{self, InstallationScope, RefSpec};
+use super::operations::OperationGuard;
+use super::manifest::{self, ManifestApp};
+use super::{bundle, cleanup, eol, flatpakref, history};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueuedTask {
+    Install {
+        ref_name: String,
+        remote: Option<String>,
+        branch: Option<String>,
+        arch: Option<String>,
+        #[serde(default)]
+        commit: Option<String>,
+    },
+    Uninstall {
+        ref_name: String,
+        branch: Option<String>,
+        arch: Option<String>,
+        #[serde(default)]
+        remove_unused: bool,
+        #[serde(default)]
+        delete_data: bool,
+    },
+    Update {
+        ref_name: String,
+        branch: Option<String>,
+        arch: Option<String>,
+        #[serde(default)]
+        commit: Option<String>,
+    },
+    UpdateAll,
+    InstallFlatpakref { path: String },
+    InstallBundle { path: String },
+    RemoveUnused { refs: Option<Vec<String>> },
+    Rebase { ref_name: String, branch: Option<String>, arch: Option<String> },
+    ImportApps { apps: Vec<ManifestApp> },
+}
+
+impl QueuedTask {
+    pub fn target(&self) -> String {
+        match self {
+            QueuedTask::Install { ref_name, branch, .. }
+            | QueuedTask::Uninstall { ref_name, branch, .. }
+            | QueuedTask::Update { ref_name, branch, .. }
+            | QueuedTask::Rebase { ref_name, branch, .. } => match branch {
+                Some(branch) => format!("{}//{}", ref_name, branch),
+                None => ref_name.clone(),
+            },
+            QueuedTask::UpdateAll => "all".to_string(),
+            QueuedTask::InstallFlatpakref { path } | QueuedTask::InstallBundle { path } => path.clone(),
+            QueuedTask::RemoveUnused { refs: Some(refs) } => refs.join(", "),
+            QueuedTask::RemoveUnused { refs: None } => "unused".to_string(),
+            QueuedTask::ImportApps { apps } => apps.iter().map(|app| app.id.as_str()).collect::<Vec<_>>().join(", "),
+        }
+    }
+
+    /// The app the job is about, for tasks that target a single app.
+    pub fn app_id(&self) -> Option<String> {
+        match self {
+            QueuedTask::Install { ref_name, .. }
+            | QueuedTask::Uninstall { ref_name, .. }
+            | QueuedTask::Update { ref_name, .. }
+            | QueuedTask::Rebase { ref_name, .. } => Some(RefSpec::parse(ref_name, None, None).id.to_string()),
+            QueuedTask::ImportApps { apps } if apps.len() == 1 => Some(apps[0].id.clone()),
+            _ => None,
+        }
+    }
+
+    /// The kind of change, named like transaction operations in the history.
+    pub fn action(&self) -> &'static str {
+        match self {
+            QueuedTask::Install { .. } | QueuedTask::InstallFlatpakref { .. } | QueuedTask::ImportApps { .. } => "install",
+            QueuedTask::InstallBundle { .. } => "install_bundle",
+            QueuedTask::Uninstall { .. } | QueuedTask::RemoveUnused { .. } => "uninstall",
+            QueuedTask::Update { .. } | QueuedTask::UpdateAll => "update",
+            QueuedTask::Rebase { .. } => "rebase",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueStatus {
+    Pending,
+    Running,
+}
+
+impl QueueStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            QueueStatus::Pending => "pending",
+            QueueStatus::Running => "running",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedOperation {
+    pub id: i64,
+    pub operation_id: String,
+    pub scope: InstallationScope,
+    pub target: String,
+    pub task: QueuedTask,
+    pub position: i64,
+    pub status: QueueStatus,
+    pub created_at: i64,
+}
+
+impl QueuedOperation {
+    fn from_row(row: &SqliteRow) -> Result<Self, InstallerError> {
+        let id: i64 = row.get("id");
+        let scope: String = row.get("scope");
+        let task: String = row.get("task");
+        let status: String = row.get("status");
+        let task: QueuedTask = serde_json::from_str(&task)
+            .map_err(|e| InstallerError::InvalidInput(format!("Invalid queued task {}: {}", id, e)))?;
+
+        Ok(Self {
+            id,
+            operation_id: id.to_string(),
+            scope: InstallationScope::parse(&scope)
+                .ok_or_else(|| InstallerError::InvalidInput(format!("Invalid scope for queued task {}: {}", id, scope)))?,
+            target: task.target(),
+            task,
+            position: row.get("position"),
+            status: if status == "running" { QueueStatus::Running } else { QueueStatus::Pending },
+            created_at: row.get("created_at"),
+        })
+    }
+}
+
+type Waiter = oneshot::Sender<Result<(), InstallerError>>;
+
+static WORKERS: OnceLock<tokio::sync::Mutex<HashSet<InstallationScope>>> = OnceLock::new();
+static WAITERS: OnceLock<Mutex<HashMap<i64, Waiter>>> = OnceLock::new();
+
+fn workers() -> &'static tokio::sync::Mutex<HashSet<InstallationScope>> {
+    WORKERS.get_or_init(|| tokio::sync::Mutex::new(HashSet::new()))
+}
+
+fn waiters() -> &'static Mutex<HashMap<i64, Waiter>> {
+    WAITERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+async fn load_queue() -> Result<Vec<QueuedOperation>, InstallerError> {
+    let pool = get_db_pool().await?;
+
+    let rows = sqlx::query("SELECT id, scope, task, position, status, created_at FROM operation_queue ORDER BY status = 'running' DESC, position, id")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to load operation queue: {}", e)))?;
+
+    rows.iter().map(QueuedOperation::from_row).collect()
+}
+
+async fn emit_queue_changed(app: &AppHandle) {
+    match load_queue().await {
+        Ok(queue) => {
+            let _ = app.emit("flatpak-queue-changed", queue);
+        }
+        Err(e) => eprintln!("Failed to emit queue change: {}", e),
+    }
+}
+
+async fn insert_job(scope: InstallationScope, task: &QueuedTask) -> Result<i64, InstallerError> {
+    let pool = get_db_pool().await?;
+    let task_json = serde_json::to_string(task)
+        .map_err(|e| InstallerError::Failed(format!("Failed to serialize queued task: {}", e)))?;
+
+    let result = sqlx::query("INSERT INTO operation_queue (scope, task, position, status, created_at) VALUES (?, ?, (SELECT COALESCE(MAX(position), -1) + 1 FROM operation_queue), ?, ?)")
+        .bind(scope.as_str())
+        .bind(task_json)
+        .bind(QueueStatus::Pending.as_str())
+        .bind(Utc::now().timestamp())
+        .execute(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to queue operation: {}", e)))?;
+
+    Ok(result.last_insert_rowid())
+}
+
+async fn next_pending(scope: InstallationScope) -> Result<Option<QueuedOperation>, InstallerError> {
+    let pool = get_db_pool().await?;
+
+    let row = sqlx::query("SELECT id, scope, task, position, status, created_at FROM operation_queue WHERE scope = ? AND status = ? ORDER BY position, id LIMIT 1")
+        .bind(scope.as_str())
+        .bind(QueueStatus::Pending.as_str())
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read operation queue: {}", e)))?;
+
+    row.as_ref().map(QueuedOperation::from_row).transpose()
+}
+
+async fn set_status(id: i64, status: QueueStatus) -> Result<(), InstallerError> {
+    let pool = get_db_pool().await?;
+
+    sqlx::query("UPDATE operation_queue SET status = ? WHERE id = ?")
+        .bind(status.as_str())
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to update queued operation: {}", e)))?;
+
+    Ok(())
+}
+
+async fn delete_job(id: i64) -> Result<(), InstallerError> {
+    let pool = get_db_pool().await?;
+
+    sqlx::query("DELETE FROM operation_queue WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to remove queued operation: {}", e)))?;
+
+    Ok(())
+}
+
+fn finish_waiter(id: i64, result: Result<(), InstallerError>) {
+    if let Some(waiter) = waiters().lock().unwrap().remove(&id) {
+        let _ = waiter.send(result);
+    }
+}
+
+fn execute(app: &AppHandle, job: &QueuedOperation) -> Result<(), InstallerError> {
+    let operation = OperationGuard::register(&job.operation_id)?;
+
+    match &job.task {
+        QueuedTask::Install { ref_name, remote, branch, arch, commit } => {
+            let spec = RefSpec::parse(ref_name, arch.as_deref(), branch.as_deref());
+            flatpak::run_install(app, &operation, ref_name, spec, job.scope, remote.as_deref(), commit.as_deref())
+        }
+        QueuedTask::Uninstall { ref_name, branch, arch, remove_unused, delete_data } => {
+            let spec = RefSpec::parse(ref_name, arch.as_deref(), branch.as_deref());
+            flatpak::run_uninstall(app, &operation, ref_name, spec, job.scope, *remove_unused, *delete_data)
+        }
+        QueuedTask::Update { ref_name, branch, arch, commit } => {
+            let spec = RefSpec::parse(ref_name, arch.as_deref(), branch.as_deref());
+            flatpak::run_update(app, &operation, ref_name, spec, job.scope, commit.as_deref())
+        }
+        QueuedTask::UpdateAll => flatpak::run_update_all(app, &operation, job.scope),
+        QueuedTask::InstallFlatpakref { path } => flatpakref::run_install_flatpakref(app, &operation, path, job.scope),
+        QueuedTask::InstallBundle { path } => bundle::run_install_bundle(app, &operation, path, job.scope),
+        QueuedTask::RemoveUnused { refs } => cleanup::run_remove_unused(app, &operation, job.scope, refs.as_deref()),
+        QueuedTask::Rebase { ref_name, branch, arch } => {
+            let spec = RefSpec::parse(ref_name, arch.as_deref(), branch.as_deref());
+            eol::run_rebase(app, &operation, ref_name, spec, job.scope)
+        }
+        QueuedTask::ImportApps { apps } => manifest::run_import(app, &operation, apps, job.scope),
+    }
+}
+
+/// Runs the queued operations of one installation strictly one after another.
+/// The worker exits once no pending job is left for its installation.
+async fn run_worker(app: AppHandle, scope: InstallationScope) {
+    loop {
+        let job = {
+            let mut running = workers().lock().await;
+            match next_pending(scope).await {
+                Ok(Some(job)) => {
+                    if let Err(e) = set_status(job.id, QueueStatus::Running).await {
+                        eprintln!("{}", e);
+                    }
+                    job
+                }
+                Ok(None) => {
+                    running.remove(&scope);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Queue worker for {} stopped: {}", scope.as_str(), e);
+                    running.remove(&scope);
+                    return;
+                }
+            }
+        };
+
+        emit_queue_changed(&app).await;
+
+        let started_at = Utc::now().timestamp();
+        let started = Instant::now();
+        let result = {
+            let app = app.clone();
+            let job = job.clone();
+            tauri::async_runtime::spawn_blocking(move || execute(&app, &job))
+                .await
+                .unwrap_or_else(|e| Err(InstallerError::Failed(format!("Queued operation panicked: {}", e))))
+        };
+
+        if let Err(e) = &result {
+            eprintln!("Queued operation {} ({}) failed: {}", job.id, job.target, e);
+            history::record_job_failure(&job, started_at, started.elapsed().as_millis() as u64, e).await;
+        }
+
+        if let Err(e) = delete_job(job.id).await {
+            eprintln!("{}", e);
+        }
+        finish_waiter(job.id, result);
+        emit_queue_changed(&app).await;
+    }
+}
+
+fn spawn_worker(running: &mut HashSet<InstallationScope>, app: &AppHandle, scope: InstallationScope) {
+    if running.insert(scope) {
+        tauri::async_runtime::spawn(run_worker(app.clone(), scope));
+    }
+}
+
+/// Queues `task` on the installation given by `scope` and waits for it to finish.
+pub async fn submit(app: &AppHandle, scope: InstallationScope, task: QueuedTask) -> Result<(), InstallerError> {
+    if scope == InstallationScope::Both {
+        return Err(InstallerError::InvalidInput("Queued operations must target either the system or the user installation".to_string()));
+    }
+
+    let receiver = {
+        let mut running = workers().lock().await;
+        let id = insert_job(scope, &task).await?;
+
+        let (sender, receiver) = oneshot::channel();
+        waiters().lock().unwrap().insert(id, sender);
+
+        spawn_worker(&mut running, app, scope);
+        receiver
+    };
+
+    emit_queue_changed(app).await;
+
+    receiver
+        .await
+        .map_err(|_| "Queued operation was dropped".to_string())?
+}
+
+/// Picks up jobs left over from a previous run. Jobs that were running when the
+/// app exited are started again from the beginning.
+pub async fn resume(app: AppHandle) -> Result<(), InstallerError> {
+    let pool = get_db_pool().await?;
+
+    sqlx::query("UPDATE operation_queue SET status = ? WHERE status = ?")
+        .bind(QueueStatus::Pending.as_str())
+        .bind(QueueStatus::Running.as_str())
+        .execute(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to reset interrupted operations: {}", e)))?;
+
+    let rows = sqlx::query("SELECT DISTINCT scope FROM operation_queue")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read operation queue: {}", e)))?;
+
+    let mut running = workers().lock().await;
+    for row in rows {
+        let scope: String = row.get("scope");
+        if let Some(scope) = InstallationScope::parse(&scope) {
+            spawn_worker(&mut running, &app, scope);
+        }
+    }
+    drop(running);
+
+    emit_queue_changed(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_operation_queue() -> Result<Vec<QueuedOperation>, InstallerError> {
+    load_queue().await
+}
+
+#[tauri::command]
+pub async fn remove_queued_operation(app: AppHandle, id: i64) -> Result<(), InstallerError> {
+    let job = {
+        let _running = workers().lock().await;
+        let pool = get_db_pool().await?;
+
+        let row = sqlx::query("SELECT id, scope, task, position, status, created_at FROM operation_queue WHERE id = ? AND status = ?")
+            .bind(id)
+            .bind(QueueStatus::Pending.as_str())
+            .fetch_optional(&pool)
+            .await
+            .map_err(|e| InstallerError::Failed(format!("Failed to read queued operation: {}", e)))?
+            .ok_or_else(|| InstallerError::InvalidInput(format!("Operation {} is not pending; cancel it instead", id)))?;
+        let job = QueuedOperation::from_row(&row)?;
+
+        delete_job(id).await?;
+        job
+    };
+
+    history::record_job_removed(&job).await;
+    finish_waiter(id, Err(InstallerError::Cancelled("Operation was removed from the queue".to_string())));
+    emit_queue_changed(&app).await;
+    Ok(())
+}
+
+/// Reorders the pending jobs of `scope`, or of every installation without
+/// one. `ids` must list exactly those jobs; the whole pending queue is then
+/// renumbered so positions stay unique.
+#[tauri::command]
+pub async fn reorder_operation_queue(app: AppHandle, ids: Vec<i64>, scope: Option<InstallationScope>) -> Result<(), InstallerError> {
+    {
+        let _running = workers().lock().await;
+        let pool = get_db_pool().await?;
+        let mut tx = pool.begin().await
+            .map_err(|e| InstallerError::Failed(format!("Failed to reorder operation queue: {}", e)))?;
+
+        let rows = sqlx::query("SELECT id, scope FROM operation_queue WHERE status = ? ORDER BY position, id")
+            .bind(QueueStatus::Pending.as_str())
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| InstallerError::Failed(format!("Failed to read operation queue: {}", e)))?;
+        let pending: Vec<(i64, bool)> = rows
+            .iter()
+            .map(|row| {
+                let job_scope: String = row.get("scope");
+                (row.get("id"), scope.is_none_or(|scope| scope.as_str() == job_scope))
+            })
+            .collect();
+
+        let expected: HashSet<i64> = pending.iter().filter(|(_, reordered)| *reordered).map(|(id, _)| *id).collect();
+        let given: HashSet<i64> = ids.iter().copied().collect();
+        if given.len() != ids.len() || given != expected {
+            return Err(InstallerError::InvalidInput("The new order must list every pending operation exactly once".to_string()));
+        }
+
+        // Jobs of other installations keep their slots; the reordered ones
+        // fill theirs in the new order.
+        let mut reordered = ids.iter();
+        for (position, (id, in_scope)) in pending.iter().enumerate() {
+            let id = if *in_scope { *reordered.next().unwrap_or(id) } else { *id };
+            sqlx::query("UPDATE operation_queue SET position = ? WHERE id = ?")
+                .bind(position as i64)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| InstallerError::Failed(format!("Failed to reorder operation queue: {}", e)))?;
+        }
+
+        tx.commit().await
+            .map_err(|e| InstallerError::Failed(format!("Failed to reorder operation queue: {}", e)))?;
+    }
+
+    emit_queue_changed(&app).await;
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use libflatpak::{Installation, Remote, prelude::*};
+use serde::{Deserialize, Serialize};
+use super::error::InstallerError;
+use super::flatpak::InstallationScope;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlatpakRemote {
+    pub name: String,
+    pub scope: InstallationScope,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub comment: Option<String>,
+    pub homepage: Option<String>,
+    pub collection_id: Option<String>,
+    pub default_branch: Option<String>,
+    pub gpg_verify: bool,
+    pub disabled: bool,
+    pub nodeps: bool,
+    pub noenumerate: bool,
+    pub prio: i32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RemoteConfig {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub disabled: Option<bool>,
+    pub nodeps: Option<bool>,
+    pub gpg_verify: Option<bool>,
+    pub prio: Option<i32>,
+}
+
+impl FlatpakRemote {
+    fn from_remote(remote: &Remote, scope: InstallationScope) -> Option<Self> {
+        Some(Self {
+            name: remote.name()?.to_string(),
+            scope,
+            url: remote.url().map(|s| s.to_string()),
+            title: remote.title().map(|s| s.to_string()),
+            comment: remote.comment().map(|s| s.to_string()),
+            homepage: remote.homepage().map(|s| s.to_string()),
+            collection_id: remote.collection_id().map(|s| s.to_string()),
+            default_branch: remote.default_branch().map(|s| s.to_string()),
+            gpg_verify: remote.is_gpg_verify(),
+            disabled: remote.is_disabled(),
+            nodeps: remote.is_nodeps(),
+            noenumerate: remote.is_noenumerate(),
+            prio: remote.prio(),
+        })
+    }
+}
+
+/// Names of the enabled remotes of an installation, highest priority first.
+pub(crate) fn enabled_remote_names(installation: &Installation, cancellable: &libflatpak::gio::Cancellable) -> Result<Vec<String>, InstallerError> {
+    let mut remotes: Vec<Remote> = installation
+        .list_remotes(Some(cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to list remotes"))?
+        .into_iter()
+        .filter(|remote| !remote.is_disabled())
+        .collect();
+
+    remotes.sort_by_key(|remote| std::cmp::Reverse(remote.prio()));
+
+    Ok(remotes
+        .iter()
+        .filter_map(|remote| remote.name().map(|s| s.to_string()))
+        .collect())
+}
+
+/// Fetches fresh summaries before resolving a ref, so new refs and branches
+/// are found. A named remote must refresh; when searching every enabled
+/// remote, one that can't be reached is skipped.
+pub(crate) fn refresh_remotes(installation: &Installation, remote: Option<&str>, cancellable: &libflatpak::gio::Cancellable) -> Result<(), InstallerError> {
+    if let Some(remote) = remote {
+        return installation
+            .update_remote_sync(remote, Some(cancellable))
+            .map(|_| ())
+            .map_err(|e| InstallerError::from_glib(&e, format!("Failed to update remote {}", remote)));
+    }
+
+    for remote in enabled_remote_names(installation, cancellable)? {
+        if let Err(e) = installation.update_remote_sync(&remote, Some(cancellable)) {
+            eprintln!("Failed to update remote {}: {}", remote, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn get_remote(installation: &Installation, name: &str) -> Result<Remote, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    installation
+        .remote_by_name(name, Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, format!("Remote '{}' not found", name)))
+}
+
+fn save_remote(installation: &Installation, remote: &Remote) -> Result<(), InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    installation
+        .modify_remote(remote, Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, "Failed to modify remote"))
+}
+
+fn is_flatpakrepo(source: &str) -> bool {
+    source.ends_with(".flatpakrepo")
+}
+
+async fn read_flatpakrepo(source: &str) -> Result<Vec<u8>, InstallerError> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = reqwest::get(source)
+            .await
+            .map_err(|e| InstallerError::Network(format!("Failed to download {}: {}", source, e)))?;
+
+        if !response.status().is_success() {
+            return Err(InstallerError::Failed(format!("Failed to download {}: HTTP {}", source, response.status())));
+        }
+
+        let bytes = response.bytes().await
+            .map_err(|e| InstallerError::Failed(format!("Failed to read {}: {}", source, e)))?;
+        Ok(bytes.to_vec())
+    } else {
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        std::fs::read(path).map_err(|e| InstallerError::Failed(format!("Failed to read {}: {}", path, e)))
+    }
+}
+
+#[tauri::command]
+pub fn list_remotes(scope: Option<InstallationScope>) -> Result<Vec<FlatpakRemote>, InstallerError> {
+    let cancellable = libflatpak::gio::Cancellable::new();
+    let mut remotes = Vec::new();
+
+    for (scope, installation) in scope.unwrap_or(InstallationScope::Both).open()? {
+        let installation_remotes = installation
+            .list_remotes(Some(&cancellable))
+            .map_err(|e| InstallerError::from_glib(&e, "Failed to list remotes"))?;
+
+        remotes.extend(installation_remotes
+            .iter()
+            .filter_map(|remote| FlatpakRemote::from_remote(remote, scope)));
+    }
+
+    Ok(remotes)
+}
+
+/// Adds a remote from a repository URL or from a `.flatpakrepo` file given as
+/// a local path or an http(s) URL. Remotes added from a bare URL verify GPG
+/// signatures unless `gpg_verify` is explicitly `false`.
+#[tauri::command]
+pub async fn add_remote(name: String, source: String, scope: Option<InstallationScope>, gpg_verify: Option<bool>) -> Result<FlatpakRemote, InstallerError> {
+    let flatpakrepo = if is_flatpakrepo(&source) || std::path::Path::new(&source).is_file() {
+        Some(read_flatpakrepo(&source).await?)
+    } else {
+        None
+    };
+
+    let scope = scope.unwrap_or_default();
+    let installation = scope.open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+
+    let remote = match flatpakrepo {
+        Some(data) => Remote::from_file(&name, &libflatpak::glib::Bytes::from_owned(data))
+            .map_err(|e| InstallerError::from_glib(&e, "Invalid .flatpakrepo file"))?,
+        None => {
+            let remote = Remote::new(&name);
+            remote.set_url(&source);
+            remote.set_gpg_verify(gpg_verify.unwrap_or(true));
+            remote
+        }
+    };
+
+    installation
+        .add_remote(&remote, false, Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to add remote '{}'", name)))?;
+
+    let remote = get_remote(&installation, &name)?;
+    FlatpakRemote::from_remote(&remote, scope)
+        .ok_or_else(|| InstallerError::Failed(format!("Failed to read remote '{}'", name)))
+}
+
+#[tauri::command]
+pub fn remove_remote(name: String, scope: Option<InstallationScope>) -> Result<(), InstallerError> {
+    let installation = scope.unwrap_or_default().open_single()?;
+    let cancellable = libflatpak::gio::Cancellable::new();
+
+    installation
+        .remove_remote(&name, Some(&cancellable))
+        .map_err(|e| InstallerError::from_glib(&e, format!("Failed to remove remote '{}'", name)))
+}
+
+#[tauri::command]
+pub fn set_remote_enabled(name: String, enabled: bool, scope: Option<InstallationScope>) -> Result<(), InstallerError> {
+    let installation = scope.unwrap_or_default().open_single()?;
+    let remote = get_remote(&installation, &name)?;
+    remote.set_disabled(!enabled);
+    save_remote(&installation, &remote)
+}
+
+#[tauri::command]
+pub fn configure_remote(name: String, config: RemoteConfig, scope: Option<InstallationScope>) -> Result<FlatpakRemote, InstallerError> {
+    let scope = scope.unwrap_or_default();
+    let installation = scope.open_single()?;
+    let remote = get_remote(&installation, &name)?;
+
+    if let Some(title) = &config.title {
+        remote.set_title(title);
+    }
+    if let Some(url) = &config.url {
+        remote.set_url(url);
+    }
+    if let Some(disabled) = config.disabled {
+        remote.set_disabled(disabled);
+    }
+    if let Some(nodeps) = config.nodeps {
+        remote.set_nodeps(nodeps);
+    }
+    // A new URL doesn't keep an earlier opt-out of signature checks.
+    if let Some(gpg_verify) = config.gpg_verify.or(config.url.as_ref().map(|_| true)) {
+        remote.set_gpg_verify(gpg_verify);
+    }
+    if let Some(prio) = config.prio {
+        remote.set_prio(prio);
+    }
+
+    save_remote(&installation, &remote)?;
+
+    FlatpakRemote::from_remote(&remote, scope)
+        .ok_or_else(|| InstallerError::Failed(format!("Failed to read remote '{}'", name)))
+}
+
+/// Assigns descending priorities so that refs are resolved from `names` in order.
+#[tauri::command]
+pub fn reorder_remotes(names: Vec<String>, scope: Option<InstallationScope>) -> Result<(), InstallerError> {
+    let installation = scope.unwrap_or_default().open_single()?;
+
+    for (index, name) in names.iter().enumerate() {
+        let remote = get_remote(&installation, name)?;
+        remote.set_prio((names.len() - index) as i32);
+        save_remote(&installation, &remote)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use sqlx::Row;
+use crate::cache::queries::get_db_pool;
+use super::approvals;
+use super::error::InstallerError;
+use super::operations::OperationErrorPolicy;
+
+pub(super) const AUTO_APPROVE_NON_WIDENING_KEY: &str = "auto_approve_non_widening_updates";
+pub(super) const OPERATION_ERROR_POLICY_KEY: &str = "operation_error_policy";
+
+pub(super) async fn load_setting(key: &str) -> Result<Option<String>, InstallerError> {
+    let pool = get_db_pool().await?;
+
+    let row = sqlx::query("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to read setting {}: {}", key, e)))?;
+
+    Ok(row.map(|row| row.get("value")))
+}
+
+pub(super) async fn store_setting(key: &str, value: &str) -> Result<(), InstallerError> {
+    let pool = get_db_pool().await?;
+
+    sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+        .bind(key)
+        .bind(value)
+        .execute(&pool)
+        .await
+        .map_err(|e| InstallerError::Failed(format!("Failed to save setting {}: {}", key, e)))?;
+
+    Ok(())
+}
+
+/// Loads the stored preferences into the installer, so jobs resumed at
+/// startup follow them before the frontend has synced anything.
+pub async fn restore() -> Result<(), InstallerError> {
+    if let Some(value) = load_setting(AUTO_APPROVE_NON_WIDENING_KEY).await? {
+        approvals::set_auto_approve(value == "true");
+    }
+    if let Some(policy) = load_setting(OPERATION_ERROR_POLICY_KEY).await?.as_deref().and_then(OperationErrorPolicy::parse) {
+        policy.set_current();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+use directories::BaseDirs;
+use libflatpak::Instance;
+use serde::Serialize;
+use super::error::InstallerError;
+use super::overrides::validate_dbus_name;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppDataReport {
+    pub app_id: String,
+    pub path: String,
+    pub exists: bool,
+    pub size: u64,
+}
+
+/// The per-app data directory flatpak creates on first run, `~/.var/app/<id>`.
+pub(super) fn data_dir(app_id: &str) -> Result<PathBuf, InstallerError> {
+    validate_dbus_name(app_id, false).map_err(|_| InstallerError::InvalidInput(format!("Invalid application id '{}'", app_id)))?;
+
+    let base_dirs = BaseDirs::new().ok_or_else(|| InstallerError::NotFound("Failed to get home directory".to_string()))?;
+    Ok(base_dirs.home_dir().join(".var").join("app").join(app_id))
+}
+
+/// Total size of the regular files under `path`, without following symlinks.
+pub(super) fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return if metadata.is_file() { metadata.len() } else { 0 };
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| dir_size(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+pub(super) fn data_report(app_id: &str) -> Result<AppDataReport, InstallerError> {
+    let path = data_dir(app_id)?;
+    let exists = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+
+    Ok(AppDataReport {
+        app_id: app_id.to_string(),
+        path: path.to_string_lossy().into_owned(),
+        exists,
+        size: if exists { dir_size(&path) } else { 0 },
+    })
+}
+
+pub(super) fn is_running(app_id: &str) -> bool {
+    Instance::all()
+        .iter()
+        .any(|instance| instance.is_running() && instance.app().is_some_and(|id| id == app_id))
+}
+
+/// Deletes the app's data directory and returns the bytes freed.
+pub(super) fn remove_data(app_id: &str) -> Result<u64, InstallerError> {
+    if is_running(app_id) {
+        return Err(InstallerError::InUse(format!("{} is running; close it before removing its data", app_id)));
+    }
+
+    let report = data_report(app_id)?;
+    if !report.exists {
+        return Ok(0);
+    }
+
+    std::fs::remove_dir_all(&report.path)
+        .map_err(|e| InstallerError::Failed(format!("Failed to remove {}: {}", report.path, e)))?;
+
+    Ok(report.size)
+}
+
+#[tauri::command]
+pub fn get_app_data_size(app_id: String) -> Result<AppDataReport, InstallerError> {
+    data_report(&app_id)
+}
+
+/// Resets an app to a fresh state by deleting its data; flatpak recreates
+/// the directory the next time the app runs.
+#[tauri::command]
+pub fn reset_app_data(app_id: String) -> Result<u64, InstallerError> {
+    remove_data(&app_id)
+}
@@ -85,6 +85,14 @@ async fn set_complete(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+fn get_launch_file() -> Result<Option<String>, String> {
+    Ok(std::env::args()
+        .skip(1)
+        .map(|arg| arg.strip_prefix("file://").map(str::to_string).unwrap_or(arg))
+        .find(|arg| std::path::Path::new(arg).is_file()))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     use tauri_plugin_sql::{Migration, MigrationKind};
@@ -108,6 +116,24 @@ pub fn run() {
             sql: include_str!("../migrations/003_add_search_indexes.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 4,
+            description: "create_operation_queue",
+            sql: include_str!("../migrations/004_create_operation_queue.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "create_history",
+            sql: include_str!("../migrations/005_create_history.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "create_settings",
+            sql: include_str!("../migrations/006_create_settings.sql"),
+            kind: MigrationKind::Up,
+        },
     ];
 
     tauri::Builder::default()
@@ -123,6 +149,16 @@ pub fn run() {
                     Err(e) => eprintln!("Background task: initiate_cache returned error: {}", e),
                 }
             });
+            let queue_handle = app.handle().clone();
+            // Restored before any command or resumed job can read the settings.
+            if let Err(e) = tauri::async_runtime::block_on(installers::settings::restore()) {
+                eprintln!("Failed to restore installer settings: {}", e);
+            }
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = installers::queue::resume(queue_handle).await {
+                    eprintln!("Failed to resume operation queue: {}", e);
+                }
+            });
             eprintln!("Tauri setup: Background task spawned, returning Ok");
             Ok(())
         })
@@ -138,6 +174,66 @@ pub fn run() {
             installers::flatpak::install_flatpak,
             installers::flatpak::uninstall_flatpak,
             installers::flatpak::is_flatpak_installed,
+            installers::flatpak::get_installed_scopes,
+            installers::inventory::list_installed_apps,
+            installers::launcher::launch_app,
+            installers::launcher::list_running_instances,
+            installers::launcher::terminate_instance,
+            installers::launcher::terminate_app,
+            installers::userdata::get_app_data_size,
+            installers::userdata::reset_app_data,
+            installers::backup::backup_app_data,
+            installers::backup::inspect_backup,
+            installers::backup::restore_app_data,
+            installers::pinning::list_remote_commits,
+            installers::pinning::mask_ref,
+            installers::pinning::unmask_ref,
+            installers::pinning::list_masked_refs,
+            installers::eol::list_eol_refs,
+            installers::eol::get_eol_status,
+            installers::eol::migrate_eol_app,
+            installers::flatpak::get_installed_branches,
+            installers::flatpak::list_remote_branches,
+            installers::flatpak::plan_install,
+            installers::permissions::get_remote_permissions,
+            installers::permissions::get_installed_permissions,
+            installers::overrides::get_app_permissions,
+            installers::overrides::grant_permission,
+            installers::overrides::revoke_permission,
+            installers::overrides::reset_permission,
+            installers::overrides::reset_app_permissions,
+            installers::approvals::check_update_permissions,
+            installers::approvals::respond_permission_request,
+            installers::approvals::list_permission_requests,
+            installers::approvals::get_auto_approve_non_widening_updates,
+            installers::approvals::set_auto_approve_non_widening_updates,
+            installers::cleanup::list_unused_refs,
+            installers::cleanup::remove_unused_refs,
+            installers::flatpak::list_flatpak_updates,
+            installers::flatpak::update_flatpak,
+            installers::flatpak::update_all,
+            installers::remotes::list_remotes,
+            installers::remotes::add_remote,
+            installers::remotes::remove_remote,
+            installers::remotes::set_remote_enabled,
+            installers::remotes::configure_remote,
+            installers::remotes::reorder_remotes,
+            installers::flatpakref::inspect_flatpakref,
+            installers::flatpakref::install_flatpakref,
+            installers::bundle::inspect_bundle,
+            installers::bundle::install_bundle,
+            installers::operations::cancel_operation,
+            installers::operations::list_running_operations,
+            installers::operations::get_operation_error_policy,
+            installers::operations::set_operation_error_policy,
+            installers::history::list_history,
+            installers::manifest::export_installed_apps,
+            installers::manifest::inspect_apps_manifest,
+            installers::manifest::import_apps_manifest,
+            installers::queue::get_operation_queue,
+            installers::queue::remove_queued_operation,
+            installers::queue::reorder_operation_queue,
+            get_launch_file,
             get_kde_theme,
             is_cache_ready_sync,
             get_cached_apps_sync,
@@ -1,38 +1,205 @@
-use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
 use std::time::Instant;
 
-pub struct SpeedCalculator {
-    last_bytes: Arc<Mutex<u64>>,
-    last_time: Arc<Mutex<Instant>>,
+/// Smoothed transfer rate. Each sample is blended into an exponential moving
+/// average weighted by the time since the previous one, so short bursts and
+/// stalls between progress callbacks don't make the speed jump around.
+pub struct RateEstimator {
+    last_bytes: u64,
+    last_time: Instant,
+    rate: Option<f64>,
 }
 
-impl SpeedCalculator {
+impl RateEstimator {
+    /// Samples closer together than this are folded into the next one.
+    const MIN_INTERVAL_SECS: f64 = 0.25;
+    /// Time constant of the average: older samples lose ~63% of their weight
+    /// after this many seconds.
+    const SMOOTHING_SECS: f64 = 3.0;
+
     pub fn new() -> Self {
         Self {
-            last_bytes: Arc::new(Mutex::new(0)),
-            last_time: Arc::new(Mutex::new(Instant::now())),
+            last_bytes: 0,
+            last_time: Instant::now(),
+            rate: None,
         }
     }
 
-    pub fn calculate_speed(&self, bytes: u64) -> f64 {
-        let now = Instant::now();
-        let mut last_bytes_guard = self.last_bytes.lock().unwrap();
-        let mut last_time_guard = self.last_time.lock().unwrap();
-        let elapsed = now.duration_since(*last_time_guard).as_secs_f64();
+    /// Records the cumulative byte count and returns the smoothed rate in
+    /// bytes per second.
+    pub fn update(&mut self, bytes: u64) -> f64 {
+        self.update_at(bytes, Instant::now())
+    }
 
-        let speed_mbps = if elapsed > 0.1 && bytes > *last_bytes_guard {
-            let bytes_diff = bytes - *last_bytes_guard;
-            (bytes_diff as f64 / elapsed) / 1_000_000.0 // Convert to MB/s
-        } else {
-            0.0
-        };
+    fn update_at(&mut self, bytes: u64, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.last_time).as_secs_f64();
+        if elapsed < Self::MIN_INTERVAL_SECS {
+            return self.rate();
+        }
 
-        if elapsed > 0.1 {
-            *last_bytes_guard = bytes;
-            *last_time_guard = now;
+        // The counter restarts when a new pull begins; treat that as a fresh start
+        // rather than a negative rate.
+        if bytes >= self.last_bytes {
+            let sample = (bytes - self.last_bytes) as f64 / elapsed;
+            let weight = 1.0 - (-elapsed / Self::SMOOTHING_SECS).exp();
+            self.rate = Some(match self.rate {
+                Some(rate) => rate + weight * (sample - rate),
+                None => sample,
+            });
         }
 
-        speed_mbps
+        self.last_bytes = bytes;
+        self.last_time = now;
+        self.rate()
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate.unwrap_or(0.0)
+    }
+
+    /// Seconds needed to transfer `remaining` bytes at the current rate, or
+    /// `None` until a rate is known.
+    pub fn eta(&self, remaining: u64) -> Option<u64> {
+        let rate = self.rate();
+        (rate > 0.0).then(|| (remaining as f64 / rate).ceil() as u64)
     }
 }
 
+impl Default for RateEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses GKeyFile-style content (`.flatpakref`, `.flatpakrepo`, metadata) into
+/// groups of key/value pairs. Comments and blank lines are skipped.
+pub fn parse_keyfile(content: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut groups: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut current_group: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(group) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            groups.entry(group.to_string()).or_default();
+            current_group = Some(group.to_string());
+            continue;
+        }
+
+        if let (Some(group), Some((key, value))) = (&current_group, line.split_once('=')) {
+            groups
+                .entry(group.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    groups
+}
+
+pub fn format_keyfile(groups: &BTreeMap<String, BTreeMap<String, String>>) -> String {
+    let mut content = String::new();
+
+    for (group, entries) in groups.iter().filter(|(_, entries)| !entries.is_empty()) {
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&format!("[{}]\n", group));
+        for (key, value) in entries {
+            content.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn after(estimator: &RateEstimator, millis: u64) -> Instant {
+        estimator.last_time + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn first_sample_sets_the_rate() {
+        let mut estimator = RateEstimator::new();
+        assert_eq!(estimator.rate(), 0.0);
+        assert_eq!(estimator.eta(1000), None);
+
+        let now = after(&estimator, 1000);
+        assert_eq!(estimator.update_at(2000, now), 2000.0);
+        assert_eq!(estimator.eta(3000), Some(2));
+        assert_eq!(estimator.eta(3001), Some(2));
+        assert_eq!(estimator.eta(4001), Some(3));
+    }
+
+    #[test]
+    fn close_samples_are_folded_into_the_next() {
+        let mut estimator = RateEstimator::new();
+        let now = after(&estimator, 1000);
+        estimator.update_at(1000, now);
+
+        let now = after(&estimator, 100);
+        assert_eq!(estimator.update_at(50_000, now), 1000.0);
+
+        // The skipped sample's bytes count towards the next one.
+        let now = after(&estimator, 1000);
+        assert!(estimator.update_at(50_000, now) > 1000.0);
+    }
+
+    #[test]
+    fn smooths_towards_new_samples() {
+        let mut estimator = RateEstimator::new();
+        let now = after(&estimator, 1000);
+        estimator.update_at(1000, now);
+
+        let now = after(&estimator, 1000);
+        let rate = estimator.update_at(1000 + 10_000, now);
+        let weight = 1.0 - (-1.0f64 / RateEstimator::SMOOTHING_SECS).exp();
+        assert!((rate - (1000.0 + weight * 9000.0)).abs() < 1e-6);
+        assert!(rate > 1000.0 && rate < 10_000.0);
+    }
+
+    #[test]
+    fn counter_reset_keeps_the_previous_rate() {
+        let mut estimator = RateEstimator::new();
+        let now = after(&estimator, 1000);
+        estimator.update_at(5000, now);
+
+        let now = after(&estimator, 1000);
+        assert_eq!(estimator.update_at(100, now), 5000.0);
+
+        let now = after(&estimator, 1000);
+        assert_eq!(estimator.update_at(5100, now), 5000.0);
+    }
+
+    #[test]
+    fn parses_keyfiles() {
+        let groups = parse_keyfile(
+            "# comment\n\
+             [Flatpak Ref]\n\
+             Name = org.example.App\n\
+             Url=https://example.org/repo?a=b\n\
+             \n\
+             [Empty]\n\
+             ignored line\n",
+        );
+
+        assert_eq!(groups["Flatpak Ref"]["Name"], "org.example.App");
+        assert_eq!(groups["Flatpak Ref"]["Url"], "https://example.org/repo?a=b");
+        assert!(groups["Empty"].is_empty());
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn keyfiles_round_trip() {
+        let content = "[Context]\nfilesystems=home:ro;\nshared=!network;\n\n[Environment]\nGTK_THEME=Adwaita:dark\n";
+        assert_eq!(format_keyfile(&parse_keyfile(content)), content);
+        assert_eq!(format_keyfile(&parse_keyfile("[Empty]\n")), "");
+    }
+}
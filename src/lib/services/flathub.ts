@@ -11,6 +11,7 @@ import {
 	type CachedApp,
 } from "./cache";
 import { convertIconPath } from "$lib/utils";
+import type { InstallationScope } from "$lib/stores/settings";
 
 const isTauri = typeof window !== "undefined" && "__TAURI_INTERNALS__" in window;
 
@@ -496,6 +497,393 @@ export const flathub = {
 	},
 };
 
-export async function isFlatpakInstalled(refId: string): Promise<boolean> {
-	return await invoke<boolean>("is_flatpak_installed", { refId });
+export async function isFlatpakInstalled(refId: string, scope: InstallationScope = "both"): Promise<boolean> {
+	return await invoke<boolean>("is_flatpak_installed", { refId, scope });
+}
+
+export async function getInstalledScopes(refId: string): Promise<InstallationScope[]> {
+	return await invoke<InstallationScope[]>("get_installed_scopes", { refId, scope: "both" });
+}
+
+export interface InstalledApp {
+	ref_name: string;
+	id: string;
+	kind: "app" | "runtime" | "unknown";
+	branch: string;
+	arch: string;
+	origin: string;
+	scope: InstallationScope;
+	installed_size: number;
+	commit: string;
+	version?: string;
+	installed_at?: number;
+	update_available: boolean;
+	masked: boolean;
+	eol?: string;
+	eol_rebase?: string;
+	cached?: CachedApp;
+}
+
+export async function listInstalledApps(scope: InstallationScope = "both"): Promise<InstalledApp[]> {
+	return await invoke<InstalledApp[]>("list_installed_apps", { scope });
+}
+
+export interface EolStatus {
+	ref_name: string;
+	scope: InstallationScope;
+	remote: string;
+	installed: boolean;
+	eol?: string;
+	eol_rebase?: string;
+}
+
+export async function listEolRefs(scope: InstallationScope = "both"): Promise<EolStatus[]> {
+	return await invoke<EolStatus[]>("list_eol_refs", { scope });
+}
+
+export async function migrateEolApp(refName: string, scope?: InstallationScope): Promise<void> {
+	await invoke("migrate_eol_app", { refName, scope });
+}
+
+export type InstallerErrorCode =
+	| "already_installed"
+	| "not_installed"
+	| "not_found"
+	| "out_of_space"
+	| "permission_denied"
+	| "authorization_denied"
+	| "network"
+	| "untrusted"
+	| "in_use"
+	| "needs_newer_flatpak"
+	| "invalid_input"
+	| "busy"
+	| "cancelled"
+	| "declined"
+	| "failed";
+
+// Shape of errors rejected by installer commands.
+export interface InstallerError {
+	code: InstallerErrorCode;
+	message: string;
+}
+
+export function isInstallerError(error: unknown): error is InstallerError {
+	return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+export function errorMessage(error: unknown): string {
+	return isInstallerError(error) ? error.message : String(error);
+}
+
+// Payload of "flatpak-operation-started".
+export interface OperationStarted {
+	operation_id: string;
+	ref: string;
+	operation_type: string;
+	index: number;
+	total: number;
+	bytes_total: number;
+}
+
+export type ProgressPhase = "estimating" | "downloading" | "deploying" | "removing" | "done";
+
+// Payload of "flatpak-progress". `percentage` covers the current operation,
+// `overall_percentage` the whole transaction.
+export interface OperationProgress {
+	operation_id: string;
+	ref: string;
+	operation_type: string;
+	index: number;
+	total: number;
+	phase: ProgressPhase;
+	status: string;
+	percentage: number;
+	bytes_transferred: number;
+	bytes_total: number;
+	bytes_per_second: number;
+	eta_seconds: number | null;
+	overall_percentage: number;
+}
+
+// Payload of "flatpak-operation-done".
+export interface OperationDone {
+	operation_id: string;
+	ref: string;
+	operation_type: string;
+	index: number;
+	total: number;
+	commit: string | null;
+	no_change: boolean;
+}
+
+// Payload of "flatpak-operation-error". When `skipped` is set the rest of the
+// transaction carries on.
+export interface OperationError {
+	operation_id: string;
+	ref: string;
+	operation_type: string;
+	index: number;
+	total: number;
+	error: InstallerError;
+	non_fatal: boolean;
+	skipped: boolean;
+}
+
+export function formatEta(seconds: number): string {
+	if (seconds < 60) return `${seconds}s`;
+	const minutes = Math.floor(seconds / 60);
+	if (minutes < 60) return `${minutes}m ${seconds % 60}s`;
+	return `${Math.floor(minutes / 60)}h ${minutes % 60}m`;
+}
+
+export type HistoryOutcome = "success" | "failed" | "skipped" | "cancelled";
+
+export interface HistoryEntry {
+	id: number;
+	operation_id: string;
+	action: string;
+	ref_name: string;
+	app_id?: string;
+	scope: InstallationScope;
+	from_commit?: string;
+	to_commit?: string;
+	bytes_downloaded: number;
+	started_at: number;
+	duration_ms: number;
+	outcome: HistoryOutcome;
+	error_code?: InstallerErrorCode;
+	error?: string;
+}
+
+export interface HistoryFilter {
+	app_id?: string;
+	ref_name?: string;
+	action?: string;
+	outcome?: HistoryOutcome;
+	scope?: InstallationScope;
+	since?: number;
+	until?: number;
+}
+
+export interface HistoryPage {
+	entries: HistoryEntry[];
+	total: number;
+	offset: number;
+	limit: number;
+}
+
+export async function listHistory(filter: HistoryFilter = {}, limit?: number, offset?: number): Promise<HistoryPage> {
+	return await invoke<HistoryPage>("list_history", { filter, limit, offset });
+}
+
+export interface ManifestApp {
+	id: string;
+	branch: string;
+	remote: string;
+	scope: InstallationScope;
+	commit?: string;
+	overrides?: Record<string, Record<string, string>>;
+}
+
+export interface AppsManifest {
+	format_version: number;
+	created_at: number;
+	apps: ManifestApp[];
+}
+
+export interface ImportPlan {
+	to_install: ManifestApp[];
+	already_installed: ManifestApp[];
+	unavailable: { app: ManifestApp; error: InstallerError }[];
+}
+
+// Payload of `flatpak-import-override-failed`, sent once per app.
+export interface OverrideFailure {
+	app_id: string;
+	error: InstallerError;
+}
+
+export async function exportInstalledApps(destination: string, scope: InstallationScope = "both"): Promise<AppsManifest> {
+	return await invoke<AppsManifest>("export_installed_apps", { destination, scope });
+}
+
+export async function inspectAppsManifest(path: string): Promise<ImportPlan> {
+	return await invoke<ImportPlan>("inspect_apps_manifest", { path });
+}
+
+export async function importAppsManifest(path: string): Promise<ImportPlan> {
+	return await invoke<ImportPlan>("import_apps_manifest", { path });
+}
+
+export interface RunningInstance {
+	instance_id: string;
+	app_id: string;
+	arch: string;
+	branch: string;
+	commit: string;
+	pid: number;
+	child_pid: number;
+}
+
+export async function launchApp(refName: string): Promise<RunningInstance> {
+	return await invoke<RunningInstance>("launch_app", { refName });
+}
+
+// Offers to close running instances of an app; resolves to false if the user keeps it open.
+export async function closeRunningApp(appId: string, displayName: string): Promise<boolean> {
+	let instances: RunningInstance[] = [];
+	try {
+		instances = await invoke<RunningInstance[]>("list_running_instances", { appId });
+	} catch (error) {
+		console.error("Failed to list running instances:", error);
+	}
+	if (instances.length === 0) return true;
+
+	if (!confirm(`${displayName} is still running. Close it and continue?`)) return false;
+	try {
+		await invoke<number>("terminate_app", { appId });
+		return true;
+	} catch (error) {
+		alert(`Could not close ${displayName}: ${errorMessage(error)}`);
+		return false;
+	}
+}
+
+export interface PlannedOperation {
+	ref_name: string;
+	operation_type: string;
+	role: string;
+	remote?: string;
+	commit?: string;
+	download_size: number;
+	installed_size: number;
+}
+
+export interface InstallPlan {
+	ref_name: string;
+	remote: string;
+	scope: InstallationScope;
+	operations: PlannedOperation[];
+	download_size: number;
+	installed_size: number;
+}
+
+export interface SandboxPermissions {
+	shared: string[];
+	sockets: string[];
+	devices: string[];
+	features: string[];
+	filesystems: string[];
+	persistent: string[];
+	session_bus: Record<string, string>;
+	system_bus: Record<string, string>;
+	environment: Record<string, string>;
+}
+
+export interface PermissionReview {
+	ref_name: string;
+	permissions: SandboxPermissions;
+	risky: string[];
+}
+
+function formatSize(bytes: number): string {
+	return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+}
+
+export async function confirmInstall(refName: string, scope: InstallationScope, displayName: string): Promise<boolean> {
+	let plan: InstallPlan;
+	try {
+		plan = await invoke<InstallPlan>("plan_install", { refName, scope });
+	} catch (error) {
+		console.error("Failed to plan install:", error);
+		return confirm(`Install ${displayName}?`);
+	}
+
+	let risky: string[] = [];
+	try {
+		const review = await invoke<PermissionReview>("get_remote_permissions", { refName, scope, remote: plan.remote });
+		risky = review.risky;
+	} catch (error) {
+		console.error("Failed to review permissions:", error);
+	}
+
+	const lines = plan.operations.map(
+		(op) => `• ${op.ref_name} (${op.role}): ${formatSize(op.download_size)} download, ${formatSize(op.installed_size)} installed`
+	);
+	const permissions = risky.length > 0
+		? `\n\nThis app requests broad access to your system:\n${risky.map((p) => `• ${p}`).join("\n")}`
+		: "";
+	return confirm(
+		`Install ${displayName} from ${plan.remote}?\n\n${lines.join("\n")}\n\nTotal: ${formatSize(plan.download_size)} download, ${formatSize(plan.installed_size)} on disk${permissions}`
+	);
+}
+
+export interface PermissionChange {
+	ref_name: string;
+	install: boolean;
+	added: SandboxPermissions;
+	removed: SandboxPermissions;
+	widens: boolean;
+	risky: string[];
+}
+
+export interface PermissionRequest {
+	request_id: string;
+	operation_id: string;
+	change: PermissionChange;
+}
+
+function listPermissions(permissions: SandboxPermissions): string[] {
+	const context = (["shared", "sockets", "devices", "features", "filesystems", "persistent"] as const).flatMap(
+		(key) => permissions[key].map((value) => `${key}: ${value}`)
+	);
+	const buses = [
+		...Object.entries(permissions.session_bus).map(([name, policy]) => `session bus: ${name} (${policy})`),
+		...Object.entries(permissions.system_bus).map(([name, policy]) => `system bus: ${name} (${policy})`),
+	];
+	const environment = Object.entries(permissions.environment).map(([name, value]) => `env: ${name}=${value}`);
+	return [...context, ...buses, ...environment];
+}
+
+export function describePermissionChange(change: PermissionChange): string {
+	const added = listPermissions(change.added);
+	const removed = listPermissions(change.removed);
+	const sections = [
+		change.install
+			? `${change.ref_name} asks for these permissions.`
+			: `The update for ${change.ref_name} changes its permissions.`,
+	];
+	if (added.length > 0) {
+		sections.push(`${change.install ? "Permissions" : "New permissions"}:\n${added.map((p) => `• ${p}`).join("\n")}`);
+	}
+	if (removed.length > 0) {
+		sections.push(`Removed permissions:\n${removed.map((p) => `• ${p}`).join("\n")}`);
+	}
+	if (change.risky.length > 0) {
+		sections.push(`This grants broad access to your system:\n${change.risky.map((p) => `• ${p}`).join("\n")}`);
+	}
+	sections.push(change.install ? "Install it?" : "Apply this update?");
+	return sections.join("\n\n");
+}
+
+export interface AppDataReport {
+	app_id: string;
+	path: string;
+	exists: boolean;
+	size: number;
+}
+
+// Asks whether to delete the app's data along with it; resolves to false when there is none.
+export async function confirmDeleteAppData(appId: string, displayName: string): Promise<boolean> {
+	let report: AppDataReport;
+	try {
+		report = await invoke<AppDataReport>("get_app_data_size", { appId });
+	} catch (error) {
+		console.error("Failed to check app data size:", error);
+		return false;
+	}
+	if (!report.exists) return false;
+
+	return confirm(`Also delete ${displayName}'s data (${formatSize(report.size)} in ${report.path})? This cannot be undone.`);
 }
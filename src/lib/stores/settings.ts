@@ -0,0 +1,80 @@
+import { writable } from "svelte/store";
+import { invoke } from "@tauri-apps/api/core";
+
+export type InstallationScope = "system" | "user" | "both";
+
+const SCOPE_STORAGE_KEY = "softwarehub.installScope";
+
+function loadInstallScope(): InstallationScope {
+	if (typeof localStorage === "undefined") return "system";
+	const stored = localStorage.getItem(SCOPE_STORAGE_KEY);
+	return stored === "user" ? "user" : "system";
+}
+
+export const installScope = writable<InstallationScope>(loadInstallScope());
+
+installScope.subscribe((scope) => {
+	if (typeof localStorage !== "undefined") {
+		localStorage.setItem(SCOPE_STORAGE_KEY, scope);
+	}
+});
+
+const AUTO_APPROVE_STORAGE_KEY = "softwarehub.autoApproveNonWideningUpdates";
+
+export const autoApproveNonWideningUpdates = writable<boolean>(
+	typeof localStorage !== "undefined" && localStorage.getItem(AUTO_APPROVE_STORAGE_KEY) === "true"
+);
+
+// The backend stores the setting so jobs resumed at startup can use it, so its
+// copy wins over the local one.
+invoke<boolean>("get_auto_approve_non_widening_updates")
+	.then((enabled) => autoApproveNonWideningUpdates.set(enabled))
+	.catch((error) => console.error("Failed to load auto-approve setting:", error))
+	.finally(() => {
+		autoApproveNonWideningUpdates.subscribe((enabled) => {
+			if (typeof localStorage !== "undefined") {
+				localStorage.setItem(AUTO_APPROVE_STORAGE_KEY, String(enabled));
+			}
+			invoke("set_auto_approve_non_widening_updates", { enabled }).catch((error) =>
+				console.error("Failed to update auto-approve setting:", error)
+			);
+		});
+	});
+
+const REMOVE_UNUSED_STORAGE_KEY = "softwarehub.removeUnusedOnUninstall";
+
+export const removeUnusedOnUninstall = writable<boolean>(
+	typeof localStorage !== "undefined" && localStorage.getItem(REMOVE_UNUSED_STORAGE_KEY) === "true"
+);
+
+removeUnusedOnUninstall.subscribe((enabled) => {
+	if (typeof localStorage !== "undefined") {
+		localStorage.setItem(REMOVE_UNUSED_STORAGE_KEY, String(enabled));
+	}
+});
+
+export type OperationErrorPolicy = "abort" | "continue_non_fatal" | "continue";
+
+const ERROR_POLICY_STORAGE_KEY = "softwarehub.operationErrorPolicy";
+
+function loadOperationErrorPolicy(): OperationErrorPolicy {
+	if (typeof localStorage === "undefined") return "continue_non_fatal";
+	const stored = localStorage.getItem(ERROR_POLICY_STORAGE_KEY);
+	return stored === "abort" || stored === "continue" ? stored : "continue_non_fatal";
+}
+
+export const operationErrorPolicy = writable<OperationErrorPolicy>(loadOperationErrorPolicy());
+
+invoke<OperationErrorPolicy>("get_operation_error_policy")
+	.then((policy) => operationErrorPolicy.set(policy))
+	.catch((error) => console.error("Failed to load operation error policy:", error))
+	.finally(() => {
+		operationErrorPolicy.subscribe((policy) => {
+			if (typeof localStorage !== "undefined") {
+				localStorage.setItem(ERROR_POLICY_STORAGE_KEY, policy);
+			}
+			invoke("set_operation_error_policy", { policy }).catch((error) =>
+				console.error("Failed to update operation error policy:", error)
+			);
+		});
+	});